@@ -0,0 +1,36 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+// a product listing where every product pulls its seller and brand in through itemref
+fn listing(products: usize) -> String {
+    let mut html = String::from("<html><body>");
+    for i in 0..products {
+        html.push_str(&format!(
+            r#"<div itemscope itemtype="https://schema.org/Product" itemref="seller-{i} brand-{i}">
+                <span itemprop="name">Product {i}</span>
+                <span itemprop="sku">{i}</span>
+            </div>"#
+        ));
+    }
+    for i in 0..products {
+        html.push_str(&format!(
+            r#"<p id="seller-{i}" itemprop="seller">Seller {i}</p>
+            <p id="brand-{i}" itemprop="brand">Brand {i}</p>"#
+        ));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn itemref(c: &mut Criterion) {
+    let mut group = c.benchmark_group("itemref");
+    for products in [100, 500, 1000] {
+        let html = listing(products);
+        group.bench_with_input(BenchmarkId::from_parameter(products), &html, |b, html| {
+            b.iter(|| microdata::parse_html("http://bittich.be/", html).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, itemref);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+// a single item with many distinct properties, each of them repeated
+fn item(properties: usize, values: usize) -> String {
+    let mut html = String::from("<html><body><div itemscope>");
+    for value in 0..values {
+        for property in 0..properties {
+            html.push_str(&format!(
+                r#"<span itemprop="p{property}">{value}</span><span itemprop="nested" itemscope><span itemprop="name">{value}</span></span>"#
+            ));
+        }
+    }
+    html.push_str("</div></body></html>");
+    html
+}
+
+fn properties(c: &mut Criterion) {
+    let mut group = c.benchmark_group("properties");
+    for properties in [100, 1000, 2000] {
+        let html = item(properties, 2);
+        group.bench_with_input(BenchmarkId::from_parameter(properties), &html, |b, html| {
+            b.iter(|| microdata::parse_html("", html).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, properties);
+criterion_main!(benches);
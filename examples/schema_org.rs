@@ -0,0 +1,36 @@
+//! Regenerates the bundled vocabulary from a schema.org release:
+//!
+//! ```sh
+//! curl -sL https://schema.org/version/latest/schemaorg-current-https.jsonld \
+//!     | cargo run --example schema_org > src/schema/schemaorg.json
+//! ```
+use std::io::{Read, stdin};
+
+use microdata::schema::Vocabulary;
+use serde_json::Value;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut jsonld = String::new();
+    stdin().read_to_string(&mut jsonld)?;
+    let vocabulary = serde_json::to_value(Vocabulary::from_schema_org_jsonld(&jsonld)?)?;
+
+    // one term per line, sorted, to keep updates reviewable
+    println!("{{");
+    println!("  \"complete\": {},", vocabulary["complete"]);
+    for (i, section) in ["types", "properties"].into_iter().enumerate() {
+        println!("  \"{section}\": {{");
+        let terms = vocabulary[section].as_object().cloned().unwrap_or_default();
+        let last = terms.len().saturating_sub(1);
+        for (j, (name, definition)) in terms.iter().enumerate() {
+            let separator = if j == last { "" } else { "," };
+            println!(
+                "    {}: {}{separator}",
+                Value::from(name.as_str()),
+                definition
+            );
+        }
+        println!("  }}{}", if i == 0 { "," } else { "" });
+    }
+    println!("}}");
+    Ok(())
+}
@@ -0,0 +1,183 @@
+//! Items of many documents, e.g. every page of a site, merged by itemid.
+//!
+//! ```
+//! use microdata::dataset::Dataset;
+//!
+//! let category = r#"<div itemscope itemid="/p/1"><span itemprop="name">Fridge</span></div>"#;
+//! let detail = r#"<div itemscope itemid="1"><data itemprop="price" value="499">499</data></div>"#;
+//!
+//! let mut dataset = Dataset::new();
+//! for (base_url, html) in [
+//!     ("https://bittich.be/fridges", category),
+//!     ("https://bittich.be/p/", detail),
+//! ] {
+//!     let items = microdata::parse_html(base_url, html).unwrap();
+//!     dataset.add_document(base_url, items);
+//! }
+//! let fridge = dataset.get("https://bittich.be/p/1").unwrap();
+//! assert_eq!(fridge.properties.len(), 2);
+//! assert_eq!(fridge.properties[1].sources, [1]);
+//! ```
+//!
+//! Parse each document with its own url as base url: the parser resolves
+//! itemids and url values against it, or against the `<base href>` of the
+//! document, so relative and absolute itemids of the same item end up in the
+//! same node. Every edge lists the documents that gave its value.
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::{
+    domain::ItemScope,
+    graph::{DocumentId, Graph, Node},
+};
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dataset {
+    documents: Vec<String>,
+    #[serde(flatten)]
+    graph: Graph,
+}
+
+impl Dataset {
+    pub fn new() -> Dataset {
+        Dataset::default()
+    }
+
+    /// Adds the items extracted from the document at `base_url`.
+    pub fn add_document(&mut self, base_url: &str, items: VecDeque<ItemScope>) -> DocumentId {
+        let document = self.documents.len();
+        self.documents.push(base_url.to_string());
+        for item in &items {
+            self.graph.add_root(item, Some(document));
+        }
+        document
+    }
+
+    /// The base urls of the documents, indexed by `DocumentId`.
+    pub fn documents(&self) -> &[String] {
+        &self.documents
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// The node of an absolute itemid.
+    pub fn get(&self, itemid: &str) -> Option<&Node> {
+        self.graph.get(itemid)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{
+        dataset::Dataset,
+        domain::ValueType,
+        graph::{Node, Target},
+        parse_html,
+    };
+
+    fn values<'a>(node: &'a Node, name: &str) -> Vec<(&'a ValueType, &'a [usize])> {
+        node.properties
+            .iter()
+            .filter(|edge| edge.name.as_str() == name)
+            .filter_map(|edge| match &edge.target {
+                Target::Value(value) => Some((value, edge.sources.as_slice())),
+                Target::Node(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dataset() {
+        let category = r#"
+        <div itemscope itemtype="https://schema.org/ItemList">
+            <div itemprop="itemListElement" itemscope itemtype="https://schema.org/Product" itemid="/p/fridge">
+                <span itemprop="name">Fridge</span>
+                <data itemprop="price" value="499">499</data>
+                <a itemprop="url" href="/p/fridge">Fridge</a>
+            </div>
+        </div>
+        "#;
+        let detail = r#"
+        <div itemscope itemtype="https://schema.org/Product" itemid="https://bittich.be/p/fridge">
+            <span itemprop="name">Fridge</span>
+            <data itemprop="price" value="459">459</data>
+            <span itemprop="color">white</span>
+            <a itemprop="url" href="fridge">Fridge</a>
+        </div>
+        "#;
+        let mut dataset = Dataset::new();
+        for (base_url, html) in [
+            ("https://bittich.be/fridges", category),
+            ("https://bittich.be/p/fridge", detail),
+        ] {
+            let items = parse_html(base_url, html).unwrap();
+            dataset.add_document(base_url, items);
+        }
+        assert_eq!(
+            dataset.documents(),
+            ["https://bittich.be/fridges", "https://bittich.be/p/fridge"]
+        );
+        assert_eq!(dataset.graph().nodes().len(), 2);
+        assert_eq!(dataset.graph().roots(), [0, 1]);
+
+        let fridge = dataset.get("https://bittich.be/p/fridge").unwrap();
+        let name = ValueType::String("Fridge".into());
+        let color = ValueType::String("white".into());
+        assert_eq!(values(fridge, "name"), [(&name, &[0, 1][..])]);
+        assert_eq!(values(fridge, "price").len(), 2);
+        assert_eq!(values(fridge, "color"), [(&color, &[1][..])]);
+        // relative urls of both documents lead to the same page
+        let url = ValueType::Url("https://bittich.be/p/fridge".into());
+        assert_eq!(values(fridge, "url"), [(&url, &[0, 1][..])]);
+        assert_eq!(dataset.graph().conflicts().len(), 1);
+
+        let json = serde_json::to_value(&dataset).unwrap();
+        assert_eq!(json["documents"][1], json!("https://bittich.be/p/fridge"));
+        assert_eq!(
+            json["nodes"][1]["itemid"],
+            json!("https://bittich.be/p/fridge")
+        );
+        assert_eq!(json["nodes"][1]["properties"][0]["sources"], json!([0, 1]));
+        assert_eq!(json["roots"], json!([0, 1]));
+    }
+
+    #[test]
+    fn test_dataset_base_element() {
+        let listing = r#"
+        <html>
+        <head><base href="https://cdn.bittich.be/p/"></head>
+        <body>
+        <div itemscope itemtype="https://schema.org/Product" itemid="fridge">
+            <span itemprop="name">Fridge</span>
+            <a itemprop="url" href="fridge">Fridge</a>
+        </div>
+        </body>
+        </html>
+        "#;
+        let detail = r#"
+        <div itemscope itemtype="https://schema.org/Product" itemid="https://cdn.bittich.be/p/fridge">
+            <span itemprop="name">Fridge</span>
+        </div>
+        "#;
+        let mut dataset = Dataset::new();
+        for (base_url, html) in [
+            ("https://bittich.be/fridges", listing),
+            ("https://bittich.be/p/fridge", detail),
+        ] {
+            let items = parse_html(base_url, html).unwrap();
+            dataset.add_document(base_url, items);
+        }
+        assert_eq!(dataset.graph().nodes().len(), 1);
+        let fridge = dataset.get("https://cdn.bittich.be/p/fridge").unwrap();
+        let name = ValueType::String("Fridge".into());
+        assert_eq!(values(fridge, "name"), [(&name, &[0, 1][..])]);
+        let url = ValueType::Url("https://cdn.bittich.be/p/fridge".into());
+        assert_eq!(values(fridge, "url"), [(&url, &[0][..])]);
+    }
+}
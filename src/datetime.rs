@@ -0,0 +1,523 @@
+//! Dates and times in the HTML microsyntaxes (2.3.5) accepted by the `datetime`
+//! attribute of `<time>`.
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeKind {
+    Year,
+    Month,
+    Date,
+    YearlessDate,
+    Week,
+    Time,
+    LocalDateTime,
+    TimeZoneOffset,
+    GlobalDateTime,
+    Duration,
+}
+
+impl TimeKind {
+    /// The xsd datatype (local name) a value of this kind maps to, if any.
+    pub fn xsd_datatype(&self) -> Option<&'static str> {
+        match self {
+            TimeKind::Year => Some("gYear"),
+            TimeKind::Month => Some("gYearMonth"),
+            TimeKind::Date => Some("date"),
+            TimeKind::YearlessDate => Some("gMonthDay"),
+            TimeKind::Time => Some("time"),
+            TimeKind::LocalDateTime | TimeKind::GlobalDateTime => Some("dateTime"),
+            TimeKind::Duration => Some("duration"),
+            TimeKind::Week | TimeKind::TimeZoneOffset => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u32,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Clock {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Temporal {
+    Year(u32),
+    Month {
+        year: u32,
+        month: u8,
+    },
+    Date(Date),
+    YearlessDate {
+        month: u8,
+        day: u8,
+    },
+    Week {
+        year: u32,
+        week: u8,
+    },
+    Time(Clock),
+    LocalDateTime(Date, Clock),
+    /// offset from UTC in minutes
+    TimeZoneOffset(i16),
+    GlobalDateTime(Date, Clock, i16),
+    Duration(Duration),
+}
+
+impl Temporal {
+    pub fn kind(&self) -> TimeKind {
+        match self {
+            Temporal::Year(_) => TimeKind::Year,
+            Temporal::Month { .. } => TimeKind::Month,
+            Temporal::Date(_) => TimeKind::Date,
+            Temporal::YearlessDate { .. } => TimeKind::YearlessDate,
+            Temporal::Week { .. } => TimeKind::Week,
+            Temporal::Time(_) => TimeKind::Time,
+            Temporal::LocalDateTime(..) => TimeKind::LocalDateTime,
+            Temporal::TimeZoneOffset(_) => TimeKind::TimeZoneOffset,
+            Temporal::GlobalDateTime(..) => TimeKind::GlobalDateTime,
+            Temporal::Duration(_) => TimeKind::Duration,
+        }
+    }
+}
+
+/// A `<time>` value, keeping the lexical form it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    lexical: String,
+    value: Temporal,
+}
+
+impl Time {
+    pub fn parse(lexical: &str) -> Option<Time> {
+        parse_temporal(lexical).map(|value| Time {
+            lexical: lexical.to_string(),
+            value,
+        })
+    }
+
+    pub fn lexical(&self) -> &str {
+        &self.lexical
+    }
+
+    pub fn value(&self) -> &Temporal {
+        &self.value
+    }
+
+    pub fn kind(&self) -> TimeKind {
+        self.value.kind()
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lexical)
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.lexical)
+    }
+}
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let lexical = String::deserialize(deserializer)?;
+        Time::parse(&lexical).ok_or_else(|| {
+            serde::de::Error::custom(format!("{lexical} is not a valid date or time string"))
+        })
+    }
+}
+
+// 4.5.14 the time element: the datetime value must be one of the following
+fn parse_temporal(s: &str) -> Option<Temporal> {
+    exactly(s, month)
+        .map(|(year, month)| Temporal::Month { year, month })
+        .or_else(|| exactly(s, date).map(Temporal::Date))
+        .or_else(|| {
+            exactly(s, yearless_date).map(|(month, day)| Temporal::YearlessDate { month, day })
+        })
+        .or_else(|| exactly(s, clock).map(Temporal::Time))
+        .or_else(|| exactly(s, local_date_time).map(|(d, c)| Temporal::LocalDateTime(d, c)))
+        .or_else(|| exactly(s, time_zone_offset).map(Temporal::TimeZoneOffset))
+        .or_else(|| {
+            exactly(s, |s| {
+                let ((date, clock), s) = local_date_time(s)?;
+                let (offset, s) = time_zone_offset(s)?;
+                Some(((date, clock, offset), s))
+            })
+            .map(|(d, c, o)| Temporal::GlobalDateTime(d, c, o))
+        })
+        .or_else(|| exactly(s, week).map(|(year, week)| Temporal::Week { year, week }))
+        .or_else(|| exactly(s, year).map(Temporal::Year))
+        .or_else(|| duration(s).map(Temporal::Duration))
+}
+
+type Parsed<'a, T> = Option<(T, &'a str)>;
+
+fn exactly<'a, T>(s: &'a str, parser: impl Fn(&'a str) -> Parsed<'a, T>) -> Option<T> {
+    parser(s)
+        .filter(|(_, rest)| rest.is_empty())
+        .map(|(v, _)| v)
+}
+
+fn digits(s: &str, min: usize, max: usize) -> Parsed<'_, u32> {
+    let len = s.bytes().take_while(u8::is_ascii_digit).count();
+    if len < min || len > max {
+        return None;
+    }
+    s[..len].parse().ok().map(|n| (n, &s[len..]))
+}
+
+fn two_digits(s: &str, min: u32, max: u32) -> Parsed<'_, u8> {
+    let (head, rest) = s.split_at_checked(2)?;
+    digits(head, 2, 2)
+        .map(|(n, _)| (n, rest))
+        .filter(|(n, _)| (min..=max).contains(n))
+        .map(|(n, rest)| (n as u8, rest))
+}
+
+fn tag(s: &str, c: char) -> Option<&str> {
+    s.strip_prefix(c)
+}
+
+fn is_leap(year: u32) -> bool {
+    year.is_multiple_of(400) || (year.is_multiple_of(4) && !year.is_multiple_of(100))
+}
+
+fn days_in_month(year: Option<u32>, month: u8) -> u8 {
+    match month {
+        2 if year.is_none_or(is_leap) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn year(s: &str) -> Parsed<'_, u32> {
+    digits(s, 4, 9).filter(|(year, _)| *year > 0)
+}
+
+fn month(s: &str) -> Parsed<'_, (u32, u8)> {
+    let (year, s) = year(s)?;
+    let (month, s) = two_digits(tag(s, '-')?, 1, 12)?;
+    Some(((year, month), s))
+}
+
+fn date(s: &str) -> Parsed<'_, Date> {
+    let ((year, month), s) = month(s)?;
+    let (day, s) = two_digits(tag(s, '-')?, 1, days_in_month(Some(year), month) as u32)?;
+    Some((Date { year, month, day }, s))
+}
+
+fn yearless_date(s: &str) -> Parsed<'_, (u8, u8)> {
+    let s = s.strip_prefix("--").unwrap_or(s);
+    let (month, s) = two_digits(s, 1, 12)?;
+    let (day, s) = two_digits(tag(s, '-')?, 1, days_in_month(None, month) as u32)?;
+    Some(((month, day), s))
+}
+
+fn clock(s: &str) -> Parsed<'_, Clock> {
+    let (hour, s) = two_digits(s, 0, 23)?;
+    let (minute, mut s) = two_digits(tag(s, ':')?, 0, 59)?;
+    let mut second = 0;
+    let mut millisecond = 0;
+    if let Some((sec, rest)) = tag(s, ':').and_then(|s| two_digits(s, 0, 59)) {
+        second = sec;
+        s = rest;
+        if let Some(fraction) = tag(s, '.') {
+            let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if !(1..=3).contains(&len) {
+                return None;
+            }
+            millisecond = format!("{:0<3}", &fraction[..len]).parse().ok()?;
+            s = &fraction[len..];
+        }
+    }
+    let clock = Clock {
+        hour,
+        minute,
+        second,
+        millisecond,
+    };
+    Some((clock, s))
+}
+
+fn local_date_time(s: &str) -> Parsed<'_, (Date, Clock)> {
+    let (date, s) = date(s)?;
+    let (clock, s) = clock(s.strip_prefix(['T', ' '])?)?;
+    Some(((date, clock), s))
+}
+
+fn time_zone_offset(s: &str) -> Parsed<'_, i16> {
+    if let Some(s) = tag(s, 'Z') {
+        return Some((0, s));
+    }
+    let (sign, s) = match s.chars().next()? {
+        '+' => (1, &s[1..]),
+        '-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (hours, s) = two_digits(s, 0, 23)?;
+    let s = s.strip_prefix(':').unwrap_or(s);
+    let (minutes, s) = two_digits(s, 0, 59)?;
+    Some((sign * (hours as i16 * 60 + minutes as i16), s))
+}
+
+// day of the week of january 1st, 0 being sunday
+fn jan_first_weekday(year: u32) -> u32 {
+    let y = year - 1;
+    (1 + 5 * (y % 4) + 4 * (y % 100) + 6 * (y % 400)) % 7
+}
+
+fn week(s: &str) -> Parsed<'_, (u32, u8)> {
+    let (year, s) = year(s)?;
+    let s = s.strip_prefix("-W")?;
+    let weekday = jan_first_weekday(year);
+    let max = if weekday == 4 || (weekday == 3 && is_leap(year)) {
+        53
+    } else {
+        52
+    };
+    let (week, s) = two_digits(s, 1, max)?;
+    Some(((year, week), s))
+}
+
+fn duration(s: &str) -> Option<Duration> {
+    if let Some(s) = s.strip_prefix('P') {
+        iso_duration(s)
+    } else {
+        component_duration(s)
+    }
+}
+
+fn seconds_with_fraction(s: &str) -> Option<Duration> {
+    let (seconds, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let (seconds, rest) = digits(seconds, 1, 19)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    let millis = match fraction {
+        "" if !s.contains('.') => 0,
+        f if (1..=3).contains(&f.len()) && f.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{f:0<3}").parse().ok()?
+        }
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds as u64) + Duration::from_millis(millis))
+}
+
+// P[nD][T[nH][nM][n[.fff]S]]
+fn iso_duration(s: &str) -> Option<Duration> {
+    let (days, time) = s.split_once('T').unwrap_or((s, ""));
+    if s.contains('T') && time.is_empty() {
+        return None;
+    }
+    let mut total = Duration::ZERO;
+    if !days.is_empty() {
+        let (n, rest) = digits(days.strip_suffix('D')?, 1, 19)?;
+        if !rest.is_empty() {
+            return None;
+        }
+        total += Duration::from_secs(n as u64 * 86400);
+    } else if time.is_empty() {
+        return None;
+    }
+    let mut time = time;
+    for (unit, seconds) in [('H', 3600), ('M', 60)] {
+        if let Some((n, rest)) = time.split_once(unit) {
+            let (n, r) = digits(n, 1, 19)?;
+            if !r.is_empty() {
+                return None;
+            }
+            total += Duration::from_secs(n as u64 * seconds);
+            time = rest;
+        }
+    }
+    if !time.is_empty() {
+        total += seconds_with_fraction(time.strip_suffix('S')?)?;
+    }
+    Some(total)
+}
+
+// e.g. "4h 18m 3s", "4h18m3s" or "4 h", each unit appearing at most once
+fn component_duration(s: &str) -> Option<Duration> {
+    let mut total = Duration::ZERO;
+    let mut seen = vec![];
+    let mut rest = s.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (n, after) = rest.split_at(end);
+        let after = after.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let mut chars = after.chars();
+        let unit = chars.next()?.to_ascii_lowercase();
+        rest = chars
+            .as_str()
+            .trim_start_matches(|c: char| c.is_ascii_whitespace());
+        if seen.contains(&unit) {
+            return None;
+        }
+        seen.push(unit);
+        total += match unit {
+            's' => seconds_with_fraction(n)?,
+            'm' | 'h' | 'd' | 'w' => {
+                let (n, rest) = digits(n, 1, 19)?;
+                if !rest.is_empty() {
+                    return None;
+                }
+                let unit_seconds = match unit {
+                    'm' => 60,
+                    'h' => 3600,
+                    'd' => 86400,
+                    _ => 604800,
+                };
+                Duration::from_secs(n as u64 * unit_seconds)
+            }
+            _ => return None,
+        };
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::datetime::{Clock, Date, Temporal, Time, TimeKind};
+
+    #[test]
+    fn test_time_kinds() {
+        for (lexical, kind) in [
+            ("2011", TimeKind::Year),
+            ("2011-11", TimeKind::Month),
+            ("2011-11-18", TimeKind::Date),
+            ("11-18", TimeKind::YearlessDate),
+            ("--11-18", TimeKind::YearlessDate),
+            ("14:54", TimeKind::Time),
+            ("14:54:39", TimeKind::Time),
+            ("14:54:39.929", TimeKind::Time),
+            ("2011-11-18T14:54:39.929", TimeKind::LocalDateTime),
+            ("2011-11-18 14:54", TimeKind::LocalDateTime),
+            ("Z", TimeKind::TimeZoneOffset),
+            ("-0800", TimeKind::TimeZoneOffset),
+            ("+05:30", TimeKind::TimeZoneOffset),
+            ("2011-11-18T14:54:39.929Z", TimeKind::GlobalDateTime),
+            ("2011-11-18 14:54-04:00", TimeKind::GlobalDateTime),
+            ("2011-W47", TimeKind::Week),
+            ("PT4H18M3S", TimeKind::Duration),
+            ("P2D", TimeKind::Duration),
+            ("4h 18m 3s", TimeKind::Duration),
+        ] {
+            assert_eq!(
+                Time::parse(lexical).map(|t| t.kind()),
+                Some(kind),
+                "{lexical}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_times() {
+        for lexical in [
+            "",
+            "May 10th 2009",
+            "2011-13",
+            "2011-02-29",
+            "0000",
+            "24:00",
+            "14:54:39.9291",
+            "2011-11-18T",
+            "2011-W53",
+            "P",
+            "PT",
+            "P1Y",
+            "4h 5h",
+            "4h5h",
+            "h",
+            "4",
+            "+2400",
+            "2024年",
+            "5é",
+            "é",
+            "4h é",
+        ] {
+            assert_eq!(Time::parse(lexical), None, "{lexical}");
+        }
+        let html = r#"<div itemscope>
+            <time itemprop="a">2024年</time><time itemprop="b" datetime="5é">5</time>
+        </div>"#;
+        let items = crate::parse_html("", html).unwrap();
+        assert_eq!(items[0].items.len(), 2);
+    }
+
+    #[test]
+    fn test_time_values() {
+        assert_eq!(
+            Time::parse("2012-02-29").map(|t| *t.value()),
+            Some(Temporal::Date(Date {
+                year: 2012,
+                month: 2,
+                day: 29
+            }))
+        );
+        assert_eq!(
+            Time::parse("2011-11-18T14:54:39.9-08:00").map(|t| *t.value()),
+            Some(Temporal::GlobalDateTime(
+                Date {
+                    year: 2011,
+                    month: 11,
+                    day: 18
+                },
+                Clock {
+                    hour: 14,
+                    minute: 54,
+                    second: 39,
+                    millisecond: 900
+                },
+                -480
+            ))
+        );
+        assert_eq!(
+            Time::parse("2015-W53").map(|t| *t.value()),
+            Some(Temporal::Week {
+                year: 2015,
+                week: 53
+            })
+        );
+        assert_eq!(
+            Time::parse("P1DT4H18M3.5S").map(|t| *t.value()),
+            Some(Temporal::Duration(Duration::from_millis(
+                ((24 + 4) * 3600 + 18 * 60 + 3) * 1000 + 500
+            )))
+        );
+        assert_eq!(
+            Time::parse("1w 2d").map(|t| *t.value()),
+            Some(Temporal::Duration(Duration::from_secs(9 * 86400)))
+        );
+        for lexical in ["4h18m3s", "4 h 18 m 3 s", " 3s 4H18m "] {
+            assert_eq!(
+                Time::parse(lexical).map(|t| *t.value()),
+                Some(Temporal::Duration(Duration::from_secs(
+                    4 * 3600 + 18 * 60 + 3
+                ))),
+                "{lexical}"
+            );
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Typed deserialization of items into user structs.
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Person {
+//!     name: String,
+//!     age: Option<u8>,
+//! }
+//! let res = microdata::parse_html(
+//!     "",
+//!     r#"<div itemscope><span itemprop="name">Neil</span><span itemprop="age">42</span></div>"#,
+//! )
+//! .unwrap();
+//! let person: Person = microdata::from_item(&res[0]).unwrap();
+//! assert_eq!(person.name, "Neil");
+//! assert_eq!(person.age, Some(42));
+//! ```
+//!
+//! Property names are matched against fields by their local name, so
+//! `http://schema.org/name` fills a `name` field. A single value deserializes
+//! into a `Vec<T>` as a one element sequence, `ValueType::Empty` and missing
+//! properties into `None`, and `ValueType::ScopeRef` into nested structs.
+use std::collections::vec_deque;
+
+use serde::{
+    Deserialize,
+    de::{
+        self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor,
+        value::BorrowedStrDeserializer,
+    },
+    forward_to_deserialize_any,
+};
+
+use crate::{
+    Error,
+    domain::{ItemScope, Numeric, Property, ValueType},
+};
+
+pub fn from_item<'a, T: Deserialize<'a>>(item_scope: &'a ItemScope) -> Result<T, Error> {
+    T::deserialize(ItemDeserializer(item_scope))
+}
+
+pub struct ItemDeserializer<'a>(pub &'a ItemScope);
+
+impl<'de> Deserializer<'de> for ItemDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(PropertiesAccess {
+            properties: self.0.items.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct PropertiesAccess<'a> {
+    properties: vec_deque::Iter<'a, Property>,
+    value: Option<&'a ValueType>,
+}
+
+impl<'de> MapAccess<'de> for PropertiesAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.properties.next() {
+            Some(property) => {
+                self.value = Some(&property.value);
+                seed.deserialize(BorrowedStrDeserializer::new(property.name.local_name()))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(ValueDeserializer(value)),
+            None => Err(Error::Deserialize("value requested before key".into())),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.properties.len())
+    }
+}
+
+pub struct ValueDeserializer<'a>(pub &'a ValueType);
+
+impl<'de> ValueDeserializer<'de> {
+    fn as_str(&self) -> Result<&'de str, Error> {
+        match self.0 {
+            ValueType::Url(s) | ValueType::String(s) => Ok(s),
+            ValueType::Meter(n) | ValueType::Number(n) => Ok(n.lexical()),
+            ValueType::Time(t) => Ok(t.lexical()),
+            ValueType::Empty => Err(<Error as de::Error>::invalid_type(
+                de::Unexpected::Unit,
+                &"a value",
+            )),
+            ValueType::Array(_) => Err(<Error as de::Error>::invalid_type(
+                de::Unexpected::Seq,
+                &"a single value",
+            )),
+            ValueType::ScopeRef(_) => Err(<Error as de::Error>::invalid_type(
+                de::Unexpected::Map,
+                &"a value",
+            )),
+        }
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let s = self.as_str()?;
+                visitor.$visit(
+                    s.trim()
+                        .parse()
+                        .map_err(|e| Error::Deserialize(format!("could not parse {s:?}: {e}")))?,
+                )
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            ValueType::Empty => visitor.visit_unit(),
+            ValueType::Array(values) => visitor.visit_seq(ValuesAccess(values.iter())),
+            ValueType::ScopeRef(item_scope) => {
+                ItemDeserializer(item_scope).deserialize_any(visitor)
+            }
+            ValueType::Url(s) | ValueType::String(s) => visitor.visit_borrowed_str(s),
+            ValueType::Time(t) => visitor.visit_borrowed_str(t.lexical()),
+            ValueType::Meter(n) | ValueType::Number(n) => match n.value() {
+                Numeric::Integer(i) => visitor.visit_i64(i),
+                Numeric::Float(f) => visitor.visit_f64(f),
+            },
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.as_str()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            ValueType::Empty => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            ValueType::Array(values) => visitor.visit_seq(ValuesAccess(values.iter())),
+            ValueType::Empty => visitor.visit_seq(ValuesAccess([].iter())),
+            value => visitor.visit_seq(ValuesAccess(std::slice::from_ref(value).iter())),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let variant = BorrowedStrDeserializer::<Error>::new(self.as_str()?);
+        variant.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct tuple_struct map struct identifier
+    }
+}
+
+struct ValuesAccess<I>(I);
+
+impl<'de, I: Iterator<Item = &'de ValueType> + ExactSizeIterator> SeqAccess<'de>
+    for ValuesAccess<I>
+{
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0
+            .next()
+            .map(|value| seed.deserialize(ValueDeserializer(value)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use crate::{from_item, parse_html};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Recipe {
+        name: String,
+        recipe_ingredient: Vec<String>,
+        recipe_yield: Option<u32>,
+        cook_time: Option<String>,
+        author: Option<Person>,
+        aggregate_rating: Option<Rating>,
+        keywords: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Person {
+        name: String,
+        url: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Rating {
+        rating_value: f64,
+        review_count: u32,
+    }
+
+    #[test]
+    fn test_from_item() {
+        let html = r#"
+            <div itemscope itemtype="https://schema.org/Recipe">
+                <h1 itemprop="name">Mom's World Famous Banana Bread</h1>
+                <span itemprop="author" itemscope itemtype="https://schema.org/Person">
+                    <span itemprop="name">John Smith</span>
+                </span>
+                <meta itemprop="cookTime" content="PT1H">
+                <span itemprop="recipeYield">12</span>
+                <ul>
+                    <li itemprop="recipeIngredient">3 or 4 ripe bananas, smashed</li>
+                    <li itemprop="recipeIngredient">1 egg</li>
+                </ul>
+                <div itemprop="aggregateRating" itemscope itemtype="https://schema.org/AggregateRating">
+                    <meter itemprop="ratingValue" min=0 value=4.5 max=5>4.5/5</meter>
+                    <span itemprop="reviewCount">11</span>
+                </div>
+                <meta itemprop="keywords" content="banana">
+            </div>
+        "#;
+        let res = parse_html("http://bittich.be/", html).unwrap();
+        let recipe: Recipe = from_item(&res[0]).unwrap();
+        assert_eq!(
+            recipe,
+            Recipe {
+                name: "Mom's World Famous Banana Bread".into(),
+                recipe_ingredient: vec!["3 or 4 ripe bananas, smashed".into(), "1 egg".into()],
+                recipe_yield: Some(12),
+                cook_time: Some("PT1H".into()),
+                author: Some(Person {
+                    name: "John Smith".into(),
+                    url: None
+                }),
+                aggregate_rating: Some(Rating {
+                    rating_value: 4.5,
+                    review_count: 11
+                }),
+                keywords: vec!["banana".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_item_invalid() {
+        let html = r#"
+            <div itemscope>
+                <span itemprop="name">Rating</span>
+                <span itemprop="ratingValue">four</span>
+                <span itemprop="reviewCount">11</span>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let err = from_item::<Rating>(&res[0]).unwrap_err();
+        assert!(err.to_string().contains("could not parse \"four\""));
+    }
+}
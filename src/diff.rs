@@ -0,0 +1,365 @@
+//! Changes between two extractions of the same page.
+//!
+//! ```
+//! use microdata::diff::{self, Change};
+//!
+//! let before = microdata::parse_html("", r#"
+//!     <div itemscope itemtype="https://schema.org/Offer" itemid="urn:offer:1">
+//!         <data itemprop="price" value="499">499</data>
+//!     </div>"#).unwrap();
+//! let after = microdata::parse_html("", r#"
+//!     <div itemscope itemtype="https://schema.org/Offer" itemid="urn:offer:1">
+//!         <data itemprop="price" value="459">459</data>
+//!     </div>"#).unwrap();
+//! let changes = diff::diff(&before, &after);
+//! assert!(matches!(&changes[..], [Change::ValueChanged { path, .. }] if path == "urn:offer:1.price"));
+//! ```
+//!
+//! Items are matched by itemid, or by itemtype and position among the items of
+//! that type when they have none. Items repeating an itemid are matched in
+//! order.
+//!
+//! Paths start with the itemid, followed by its occurrence when it repeats
+//! (`urn:org:acme[1]`), or with the itemtype and position
+//! (`https://schema.org/Product[0]`). Then come the property names. Values of
+//! multi-valued properties are compared by position and get their index in the
+//! path (`keywords[1]`).
+use std::collections::{HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::domain::{ItemScope, Name, ValueType};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "change")]
+pub enum Change {
+    ItemAdded {
+        path: String,
+        item: ItemScope,
+    },
+    ItemRemoved {
+        path: String,
+        item: ItemScope,
+    },
+    /// an item with the same itemid has other itemtypes
+    TypeChanged {
+        path: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    PropertyAdded {
+        path: String,
+        value: ValueType,
+    },
+    PropertyRemoved {
+        path: String,
+        value: ValueType,
+    },
+    ValueAdded {
+        path: String,
+        value: ValueType,
+    },
+    ValueRemoved {
+        path: String,
+        value: ValueType,
+    },
+    ValueChanged {
+        path: String,
+        old: ValueType,
+        new: ValueType,
+    },
+}
+
+/// The changes that turn `old` into `new`, following the order of `new`,
+/// removed items coming last.
+pub fn diff(old: &VecDeque<ItemScope>, new: &VecDeque<ItemScope>) -> Vec<Change> {
+    let mut changes = vec![];
+    let old_keys = keys(old);
+    let mut matched = vec![false; old.len()];
+    let by_key: HashMap<&Key, usize> = old_keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key, i))
+        .collect();
+    for (item, key) in new.iter().zip(keys(new)) {
+        match by_key.get(&key).copied() {
+            Some(i) => {
+                matched[i] = true;
+                diff_item(&key.to_string(), &old[i], item, &mut changes);
+            }
+            None => changes.push(Change::ItemAdded {
+                path: key.to_string(),
+                item: item.clone(),
+            }),
+        }
+    }
+    for ((item, key), matched) in old.iter().zip(old_keys).zip(matched) {
+        if !matched {
+            changes.push(Change::ItemRemoved {
+                path: key.to_string(),
+                item: item.clone(),
+            });
+        }
+    }
+    changes
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Key {
+    /// the itemid and how many items before had it
+    Id(String, usize),
+    Type(String, usize),
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Id(itemid, 0) => write!(f, "{itemid}"),
+            Key::Id(itemid, occurrence) => write!(f, "{itemid}[{occurrence}]"),
+            Key::Type(itemtype, position) => write!(f, "{itemtype}[{position}]"),
+        }
+    }
+}
+
+fn keys(items: &VecDeque<ItemScope>) -> Vec<Key> {
+    let mut positions = HashMap::new();
+    let mut occurrences = HashMap::new();
+    items
+        .iter()
+        .map(|item| match &item.itemid {
+            Some(itemid) => {
+                let occurrence = occurrences.entry(itemid.as_str()).or_insert(0);
+                *occurrence += 1;
+                Key::Id(itemid.clone(), *occurrence - 1)
+            }
+            None => {
+                let itemtype = item.itemtype.join(" ");
+                let position = positions.entry(itemtype.clone()).or_insert(0);
+                *position += 1;
+                Key::Type(itemtype, *position - 1)
+            }
+        })
+        .collect()
+}
+
+fn diff_item(path: &str, old: &ItemScope, new: &ItemScope, changes: &mut Vec<Change>) {
+    if old.itemtype != new.itemtype {
+        changes.push(Change::TypeChanged {
+            path: path.to_string(),
+            old: old.itemtype.clone(),
+            new: new.itemtype.clone(),
+        });
+    }
+    let old_values = values_by_name(old);
+    let new_values = values_by_name(new);
+    for (name, new_values) in &new_values {
+        let path = format!("{path}.{}", name.as_str());
+        let Some((_, old_values)) = old_values.iter().find(|(n, _)| n == name) else {
+            changes.push(Change::PropertyAdded {
+                path,
+                value: to_value(new_values),
+            });
+            continue;
+        };
+        if let ([old], [new]) = (old_values.as_slice(), new_values.as_slice()) {
+            diff_value(&path, old, new, changes);
+            continue;
+        }
+        for i in 0..old_values.len().max(new_values.len()) {
+            let path = format!("{path}[{i}]");
+            match (old_values.get(i), new_values.get(i)) {
+                (Some(old), Some(new)) => diff_value(&path, old, new, changes),
+                (Some(old), None) => changes.push(Change::ValueRemoved {
+                    path,
+                    value: (*old).clone(),
+                }),
+                (None, Some(new)) => changes.push(Change::ValueAdded {
+                    path,
+                    value: (*new).clone(),
+                }),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+    for (name, old_values) in old_values {
+        if !new_values.iter().any(|(n, _)| *n == name) {
+            changes.push(Change::PropertyRemoved {
+                path: format!("{path}.{}", name.as_str()),
+                value: to_value(&old_values),
+            });
+        }
+    }
+}
+
+fn diff_value(path: &str, old: &ValueType, new: &ValueType, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (ValueType::ScopeRef(old), ValueType::ScopeRef(new))
+            if old.itemid == new.itemid
+                && (old.itemid.is_some() || old.itemtype == new.itemtype) =>
+        {
+            diff_item(path, old, new, changes)
+        }
+        (old, new) if old != new => changes.push(Change::ValueChanged {
+            path: path.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+// the values of each property name, in the order the names first appear
+fn values_by_name(item: &ItemScope) -> Vec<(&Name, Vec<&ValueType>)> {
+    let mut names: Vec<(&Name, Vec<&ValueType>)> = vec![];
+    for property in &item.items {
+        let values = match names.iter_mut().find(|(name, _)| *name == &property.name) {
+            Some((_, values)) => values,
+            None => {
+                names.push((&property.name, vec![]));
+                &mut names.last_mut().expect("just pushed").1
+            }
+        };
+        match &property.value {
+            ValueType::Array(array) => values.extend(array),
+            value => values.push(value),
+        }
+    }
+    names
+}
+
+fn to_value(values: &[&ValueType]) -> ValueType {
+    match values {
+        [value] => (*value).clone(),
+        values => ValueType::Array(values.iter().map(|v| (*v).clone()).collect()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{
+        diff::{Change, diff},
+        domain::{Number, ValueType},
+        parse_html,
+    };
+
+    #[test]
+    fn test_diff() {
+        let before = parse_html(
+            "",
+            r#"
+        <div itemscope itemtype="https://schema.org/Product" itemid="urn:sku:fridge">
+            <span itemprop="name">Fridge</span>
+            <span itemprop="color">white</span>
+            <span itemprop="color">grey</span>
+            <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                <data itemprop="price" value="499">499</data>
+                <link itemprop="availability" href="https://schema.org/InStock">
+            </div>
+        </div>
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Oven</span>
+        </div>
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Toaster</span>
+        </div>
+        "#,
+        )
+        .unwrap();
+        let after = parse_html(
+            "",
+            r#"
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Oven</span>
+            <meta itemprop="sku" content="oven-1">
+        </div>
+        <div itemscope itemtype="https://schema.org/Product" itemid="urn:sku:fridge">
+            <span itemprop="name">Fridge</span>
+            <span itemprop="color">white</span>
+            <span itemprop="color">black</span>
+            <span itemprop="color">grey</span>
+            <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                <data itemprop="price" value="459">459</data>
+                <link itemprop="availability" href="https://schema.org/OutOfStock">
+            </div>
+        </div>
+        "#,
+        )
+        .unwrap();
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes[..3],
+            [
+                Change::PropertyAdded {
+                    path: "https://schema.org/Product[0].sku".into(),
+                    value: ValueType::String("oven-1".into()),
+                },
+                Change::ValueChanged {
+                    path: "urn:sku:fridge.color[1]".into(),
+                    old: ValueType::String("grey".into()),
+                    new: ValueType::String("black".into()),
+                },
+                Change::ValueAdded {
+                    path: "urn:sku:fridge.color[2]".into(),
+                    value: ValueType::String("grey".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            changes[3],
+            Change::ValueChanged {
+                path: "urn:sku:fridge.offers.price".into(),
+                old: ValueType::Number(Number::parse("499").unwrap()),
+                new: ValueType::Number(Number::parse("459").unwrap()),
+            }
+        );
+        assert!(
+            matches!(&changes[4], Change::ValueChanged { path, .. } if path == "urn:sku:fridge.offers.availability")
+        );
+        assert!(
+            matches!(&changes[5], Change::ItemRemoved { path, item } if path == "https://schema.org/Product[1]" && item.value("name") == Some(&ValueType::String("Toaster".into())))
+        );
+        assert_eq!(changes.len(), 6);
+
+        let json = serde_json::to_value(&changes[0]).unwrap();
+        assert_eq!(json["change"], json!("propertyAdded"));
+        assert_eq!(json["path"], json!("https://schema.org/Product[0].sku"));
+        assert!(diff(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_diff_repeated_itemids() {
+        let html = r#"
+        <header itemscope itemtype="https://schema.org/Organization" itemid="urn:org:acme">
+            <span itemprop="name">ACME</span>
+        </header>
+        <footer itemscope itemtype="https://schema.org/Organization" itemid="urn:org:acme">
+            <span itemprop="telephone">555-0100</span>
+        </footer>
+        "#;
+        let items = parse_html("", html).unwrap();
+        assert!(diff(&items, &items).is_empty());
+
+        let mut fewer = items.clone();
+        fewer.pop_back();
+        assert!(
+            matches!(&diff(&items, &fewer)[..], [Change::ItemRemoved { path, item }] if path == "urn:org:acme[1]" && item.value("telephone").is_some())
+        );
+
+        // the same change in either item gets its own path
+        let changed = parse_html(
+            "",
+            &html.replace("ACME", "Acme").replace("555-0100", "555-0199"),
+        )
+        .unwrap();
+        let paths = diff(&items, &changed)
+            .iter()
+            .map(|change| match change {
+                Change::ValueChanged { path, .. } => path.clone(),
+                change => panic!("unexpected {change:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["urn:org:acme.name", "urn:org:acme[1].telephone"]);
+    }
+}
@@ -1,13 +1,18 @@
-use std::{collections::VecDeque, sync::Arc};
+use std::{cmp::Ordering, collections::VecDeque, fmt, sync::Arc};
 
 use serde::{Deserialize, Serialize};
+use url::Url;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+use crate::{datetime::Time, error::Warning};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ItemScope {
     pub itemid: Option<String>,
     pub itemtype: Vec<String>,
     pub items: VecDeque<Property>,
+    #[serde(default, skip_serializing_if = "Origin::is_empty")]
+    pub origin: Origin,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Clone)]
@@ -18,19 +23,242 @@ pub enum ValueType {
     Array(VecDeque<ValueType>),
     Url(String),
     String(String),
-    Meter(String),
-    Time(String),
+    Meter(Number),
+    /// a `<data>` value that is a valid floating-point number
+    Number(Number),
+    /// a `<time>` value in one of the date and time microsyntaxes
+    Time(Time),
     ScopeRef(Arc<ItemScope>),
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+impl ValueType {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ValueType::Url(s) | ValueType::String(s) => Some(s),
+            ValueType::Meter(n) | ValueType::Number(n) => Some(n.lexical()),
+            ValueType::Time(t) => Some(t.lexical()),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            ValueType::Meter(n) | ValueType::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_item(&self) -> Option<&ItemScope> {
+        match self {
+            ValueType::ScopeRef(item) => Some(item),
+            _ => None,
+        }
+    }
+
+    pub fn as_time(&self) -> Option<&Time> {
+        match self {
+            ValueType::Time(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(Number::as_f64)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numeric {
+    Integer(i64),
+    Float(f64),
+}
+
+/// A valid floating-point number (2.3.4.3), keeping the lexical form it was parsed from.
+#[derive(Debug, Clone)]
+pub struct Number {
+    lexical: String,
+    value: Numeric,
+}
+
+impl Number {
+    /// `-?[0-9]+(\.[0-9]+)?([eE][+-]?[0-9]+)?`, anything else is rejected, and
+    /// so are numbers too large for an `f64`.
+    pub fn parse(lexical: &str) -> Option<Number> {
+        fn digits(s: &str) -> usize {
+            s.bytes().take_while(u8::is_ascii_digit).count()
+        }
+        let mut rest = lexical.strip_prefix('-').unwrap_or(lexical);
+        let integer = digits(rest);
+        if integer == 0 {
+            return None;
+        }
+        rest = &rest[integer..];
+        let mut is_integer = true;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let len = digits(fraction);
+            if len == 0 {
+                return None;
+            }
+            rest = &fraction[len..];
+            is_integer = false;
+        }
+        if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            let len = digits(exponent);
+            if len == 0 {
+                return None;
+            }
+            rest = &exponent[len..];
+            is_integer = false;
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+        let value = match lexical.parse::<i64>() {
+            Ok(i) if is_integer => Numeric::Integer(i),
+            _ => Numeric::Float(lexical.parse::<f64>().ok().filter(|f| f.is_finite())?),
+        };
+        Some(Number {
+            lexical: lexical.to_string(),
+            value,
+        })
+    }
+
+    pub fn lexical(&self) -> &str {
+        &self.lexical
+    }
+
+    pub fn value(&self) -> Numeric {
+        self.value
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match self.value {
+            Numeric::Integer(i) => i as f64,
+            Numeric::Float(f) => f,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value {
+            Numeric::Integer(i) => Some(i),
+            Numeric::Float(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lexical)
+    }
+}
+
+// the value is derived from the lexical form, so comparing the latter is enough for equality
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.lexical == other.lexical
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_f64()
+            .total_cmp(&other.as_f64())
+            .then_with(|| self.lexical.cmp(&other.lexical))
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.lexical)
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let lexical = String::deserialize(deserializer)?;
+        Number::parse(&lexical).ok_or_else(|| {
+            serde::de::Error::custom(format!("{lexical} is not a valid floating-point number"))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Property {
     pub name: Name,
     pub value: ValueType,
+    #[serde(default, skip_serializing_if = "Origin::is_empty")]
+    pub origin: Origin,
+}
+
+impl Property {
+    pub fn new(name: Name, value: ValueType) -> Self {
+        Property {
+            name,
+            value,
+            origin: Origin::default(),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+/// Where in the document something was extracted from, recorded when
+/// `ParserOptions::origins` is set. Two origins always compare equal, so it
+/// never takes part in comparing extractions.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Origin {
+    /// index of the element in tree order, among all the elements of the document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    /// css-like path of the element, e.g. `html > body > div#amanda > span`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// line of the input the element starts on, counting from 1. The parser
+    /// does not report byte offsets, so none are recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+impl Origin {
+    pub fn is_empty(&self) -> bool {
+        self.position.is_none() && self.path.is_none() && self.line.is_none()
+    }
+}
+
+impl PartialEq for Origin {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Origin {}
+
+impl PartialOrd for Origin {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Origin {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type", content = "value")]
 pub enum Name {
@@ -38,7 +266,133 @@ pub enum Name {
     String(String),
 }
 
+impl Name {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Name::Url(name) | Name::String(name) => name,
+        }
+    }
+
+    /// The name with urls reduced to their last segment, e.g. `name` for
+    /// `https://schema.org/name`.
+    pub fn local_name(&self) -> &str {
+        match self {
+            Name::String(name) => name,
+            Name::Url(url) => url
+                .trim_end_matches(['/', '#'])
+                .rsplit(['/', '#'])
+                .next()
+                .unwrap_or(url),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Config<'a> {
-    pub base_url: &'a str,
+    pub base_url: Option<&'a Url>,
+    /// skip invalid properties, broken itemrefs and cycles instead of failing
+    pub lenient: bool,
+    pub keep_invalid_itemtypes: bool,
+    pub max_depth: Option<usize>,
+    pub normalize_whitespace: bool,
+    pub value_typing: ValueTyping,
+    pub origins: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValueTyping {
+    /// `<meter>`, `<time>`... values get their own `ValueType` variant
+    #[default]
+    Typed,
+    /// every non url value is kept as a `ValueType::String`
+    Text,
+}
+
+/// Per crawl parsing options, turned into a `Config` by `parse_html_with`.
+///
+/// ```
+/// use microdata::domain::ParserOptions;
+///
+/// let options = ParserOptions::new()
+///     .base_url("http://bittich.be/")
+///     .lenient(true)
+///     .max_depth(8);
+/// let extraction = microdata::parse_html_with(&options, "<div itemscope></div>").unwrap();
+/// assert_eq!(extraction.items.len(), 1);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ParserOptions {
+    pub base_url: String,
+    pub lenient: bool,
+    /// keep `itemtype` tokens that are not absolute urls
+    pub keep_invalid_itemtypes: bool,
+    /// maximum nesting of items, top level items being at depth 1
+    pub max_depth: Option<usize>,
+    /// collapse runs of whitespace in text values into a single space
+    pub normalize_whitespace: bool,
+    pub value_typing: ValueTyping,
+    /// record the `Origin` of items and properties
+    pub origins: bool,
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    pub fn keep_invalid_itemtypes(mut self, keep_invalid_itemtypes: bool) -> Self {
+        self.keep_invalid_itemtypes = keep_invalid_itemtypes;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn normalize_whitespace(mut self, normalize_whitespace: bool) -> Self {
+        self.normalize_whitespace = normalize_whitespace;
+        self
+    }
+
+    pub fn value_typing(mut self, value_typing: ValueTyping) -> Self {
+        self.value_typing = value_typing;
+        self
+    }
+
+    pub fn origins(mut self, origins: bool) -> Self {
+        self.origins = origins;
+        self
+    }
+
+    pub fn config<'a>(&self, base_url: Option<&'a Url>) -> Config<'a> {
+        Config {
+            base_url,
+            lenient: self.lenient,
+            keep_invalid_itemtypes: self.keep_invalid_itemtypes,
+            max_depth: self.max_depth,
+            normalize_whitespace: self.normalize_whitespace,
+            value_typing: self.value_typing,
+            origins: self.origins,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Extraction {
+    pub items: VecDeque<ItemScope>,
+    pub warnings: Vec<Warning>,
 }
@@ -0,0 +1,155 @@
+use std::{collections::BTreeSet, fmt};
+
+use serde::Serialize;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An `itemref` points back to an element that is already being crawled.
+    CycleDetected {
+        itemref: String,
+        element_id: Option<String>,
+        ids: BTreeSet<String>,
+    },
+    /// An `itemprop` token that is neither an absolute url nor a name without `.` or `:`.
+    InvalidPropertyName {
+        itemprop: String,
+        element_id: Option<String>,
+    },
+    /// An `itemref` token that cannot be looked up.
+    InvalidItemRef {
+        itemref: String,
+        element_id: Option<String>,
+        reason: String,
+    },
+    /// An item with an `itemprop` that is not a property of any other item.
+    UnattachedItem {
+        itemprop: String,
+        element_id: Option<String>,
+    },
+    /// Items nested deeper than `ParserOptions::max_depth`.
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+        element_id: Option<String>,
+    },
+    /// A `<meter>` value that is not a valid floating-point number.
+    InvalidNumber {
+        value: String,
+        element_id: Option<String>,
+    },
+    /// Items that do not fit the type they are deserialized into.
+    Deserialize(String),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    Io(std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum ErrorKind {
+    CycleDetected,
+    InvalidPropertyName,
+    InvalidItemRef,
+    UnattachedItem,
+    LimitExceeded,
+    InvalidNumber,
+    Deserialize,
+    Json,
+    Toml,
+    Io,
+}
+
+/// A problem that was skipped over when parsing in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// css-like path of the element that caused it, e.g. `html > body > div#amanda > span`,
+    /// empty when it is about the whole document
+    pub location: String,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::CycleDetected { .. } => ErrorKind::CycleDetected,
+            Error::InvalidPropertyName { .. } => ErrorKind::InvalidPropertyName,
+            Error::InvalidItemRef { .. } => ErrorKind::InvalidItemRef,
+            Error::UnattachedItem { .. } => ErrorKind::UnattachedItem,
+            Error::LimitExceeded { .. } => ErrorKind::LimitExceeded,
+            Error::InvalidNumber { .. } => ErrorKind::InvalidNumber,
+            Error::Deserialize(_) => ErrorKind::Deserialize,
+            Error::Json(_) => ErrorKind::Json,
+            Error::Toml(_) => ErrorKind::Toml,
+            Error::Io(_) => ErrorKind::Io,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::CycleDetected { ids, .. } => write!(f, "cycle detected! {ids:?}"),
+            Error::InvalidPropertyName { itemprop, .. } => {
+                write!(f, "itemprop {itemprop} is not a valid property")
+            }
+            Error::InvalidItemRef {
+                itemref, reason, ..
+            } => write!(f, "itemref {itemref} is not valid: {reason}"),
+            Error::UnattachedItem { itemprop, .. } => {
+                write!(
+                    f,
+                    "item with itemprop {itemprop} is not a property of any item"
+                )
+            }
+            Error::LimitExceeded { limit, max, .. } => {
+                write!(f, "{limit} limit exceeded (max {max})")
+            }
+            Error::InvalidNumber { value, .. } => {
+                write!(f, "{value} is not a valid floating-point number")
+            }
+            Error::Deserialize(msg) => f.write_str(msg),
+            Error::Json(e) => write!(f, "{e}"),
+            Error::Toml(e) => write!(f, "{e}"),
+            Error::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Json(e) => Some(e),
+            Error::Toml(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Deserialize(msg.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::Toml(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
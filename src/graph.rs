@@ -0,0 +1,341 @@
+//! Items of a page as a graph, where the items sharing an itemid are one node.
+//!
+//! ```
+//! use microdata::graph::Graph;
+//!
+//! let html = r#"
+//!     <header itemscope itemtype="https://schema.org/Organization" itemid="urn:org:acme">
+//!         <span itemprop="name">ACME</span>
+//!     </header>
+//!     <footer itemscope itemtype="https://schema.org/Corporation" itemid="urn:org:acme">
+//!         <span itemprop="telephone">555-0100</span>
+//!     </footer>
+//! "#;
+//! let items = microdata::parse_html("", html).unwrap();
+//! let graph = Graph::from_items(&items);
+//! assert_eq!(graph.nodes().len(), 1);
+//! let acme = graph.get("urn:org:acme").unwrap();
+//! assert_eq!(acme.itemtype.len(), 2);
+//! assert_eq!(acme.properties.len(), 2);
+//! ```
+//!
+//! Merging keeps the union of the itemtypes and of the property values. When
+//! both occurrences give a single, different value for the same property, both
+//! values are kept and a `Conflict` is reported.
+use std::collections::{HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::domain::{ItemScope, Name, ValueType};
+
+/// Index of a node in `Graph::nodes`.
+pub type NodeId = usize;
+
+/// Index of a document in `Dataset::documents`.
+///
+/// [`Dataset::documents`]: crate::dataset::Dataset::documents
+pub type DocumentId = usize;
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Graph {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+    conflicts: Vec<Conflict>,
+    #[serde(skip)]
+    ids: HashMap<String, NodeId>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub itemid: Option<String>,
+    pub itemtype: Vec<String>,
+    pub properties: Vec<Edge>,
+}
+
+/// One value of a property. Multi-valued properties have an edge per value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Edge {
+    pub name: Name,
+    pub target: Target,
+    /// the documents that gave this value, when built by a `Dataset`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<DocumentId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Target {
+    Value(ValueType),
+    Node(NodeId),
+}
+
+/// Two occurrences of an item giving different values to a property they
+/// both have a single value for.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub itemid: String,
+    pub name: Name,
+    pub existing: Target,
+    pub incoming: Target,
+}
+
+impl Graph {
+    pub fn from_items(items: &VecDeque<ItemScope>) -> Graph {
+        let mut graph = Graph::default();
+        for item in items {
+            graph.add_item(item);
+        }
+        graph
+    }
+
+    /// Adds a top level item and the items nested in it, merging them into
+    /// the nodes that have the same itemid.
+    pub fn add_item(&mut self, item: &ItemScope) -> NodeId {
+        self.add_root(item, None)
+    }
+
+    pub(crate) fn add_root(&mut self, item: &ItemScope, source: Option<DocumentId>) -> NodeId {
+        let id = self.add(item, source);
+        if !self.roots.contains(&id) {
+            self.roots.push(id);
+        }
+        id
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn get(&self, itemid: &str) -> Option<&Node> {
+        self.ids.get(itemid).map(|&id| &self.nodes[id])
+    }
+
+    /// The nodes of the top level items, in the order they were first met.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    fn add(&mut self, item: &ItemScope, source: Option<DocumentId>) -> NodeId {
+        // the node is created before the nested items, to keep document order
+        let existing = item.itemid.as_ref().and_then(|itemid| self.ids.get(itemid));
+        let (id, merged) = match existing {
+            Some(&id) => (id, true),
+            None => {
+                let id = self.nodes.len();
+                if let Some(itemid) = &item.itemid {
+                    self.ids.insert(itemid.clone(), id);
+                }
+                self.nodes.push(Node {
+                    itemid: item.itemid.clone(),
+                    ..Default::default()
+                });
+                (id, false)
+            }
+        };
+
+        let mut incoming: Vec<(&Name, Vec<Target>)> = vec![];
+        let mut incoming_index: HashMap<&Name, usize> = HashMap::new();
+        for property in &item.items {
+            let mut targets = vec![];
+            self.targets(&property.value, source, &mut targets);
+            match incoming_index.get(&property.name) {
+                Some(&i) => incoming[i].1.extend(targets),
+                None => {
+                    incoming_index.insert(&property.name, incoming.len());
+                    incoming.push((&property.name, targets));
+                }
+            }
+        }
+
+        let node = &mut self.nodes[id];
+        if !merged {
+            // an item nested in itself through its itemid was merged already
+            let nested = std::mem::take(&mut node.properties);
+            let nested_types = std::mem::replace(&mut node.itemtype, item.itemtype.clone());
+            for itemtype in nested_types {
+                if !node.itemtype.contains(&itemtype) {
+                    node.itemtype.push(itemtype);
+                }
+            }
+            node.properties = incoming
+                .into_iter()
+                .flat_map(|(name, targets)| {
+                    targets.into_iter().map(|target| Edge {
+                        name: name.clone(),
+                        target,
+                        sources: source.into_iter().collect(),
+                    })
+                })
+                .collect();
+            node.properties.extend(nested);
+            return id;
+        }
+
+        for itemtype in &item.itemtype {
+            if !node.itemtype.contains(itemtype) {
+                node.itemtype.push(itemtype.clone());
+            }
+        }
+        // the edges of each name, so that merging does not scan every edge
+        let mut edges_by_name: HashMap<Name, Vec<usize>> = HashMap::new();
+        for (i, edge) in node.properties.iter().enumerate() {
+            edges_by_name.entry(edge.name.clone()).or_default().push(i);
+        }
+        for (name, targets) in incoming {
+            let edges = edges_by_name.entry(name.clone()).or_default();
+            if let ([existing], [incoming]) = (edges.as_slice(), targets.as_slice())
+                && node.properties[*existing].target != *incoming
+            {
+                self.conflicts.push(Conflict {
+                    itemid: node.itemid.clone().unwrap_or_default(),
+                    name: name.clone(),
+                    existing: node.properties[*existing].target.clone(),
+                    incoming: incoming.clone(),
+                });
+            }
+            for target in targets {
+                match edges.iter().find(|&&i| node.properties[i].target == target) {
+                    Some(&i) => {
+                        let edge = &mut node.properties[i];
+                        if let Some(source) = source
+                            && !edge.sources.contains(&source)
+                        {
+                            edge.sources.push(source);
+                        }
+                    }
+                    None => {
+                        edges.push(node.properties.len());
+                        node.properties.push(Edge {
+                            name: name.clone(),
+                            target,
+                            sources: source.into_iter().collect(),
+                        });
+                    }
+                }
+            }
+        }
+        id
+    }
+
+    fn targets(
+        &mut self,
+        value: &ValueType,
+        source: Option<DocumentId>,
+        targets: &mut Vec<Target>,
+    ) {
+        match value {
+            ValueType::Array(values) => {
+                for value in values {
+                    self.targets(value, source, targets);
+                }
+            }
+            ValueType::ScopeRef(item) => targets.push(Target::Node(self.add(item, source))),
+            value => targets.push(Target::Value(value.clone())),
+        }
+    }
+}
+
+impl Node {
+    /// Targets of the edges called `name`, matched like `ItemScope::values`.
+    pub fn targets<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Target> {
+        self.properties
+            .iter()
+            .filter(move |edge| edge.name.as_str() == name || edge.name.local_name() == name)
+            .map(|edge| &edge.target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        domain::{Name, ValueType},
+        graph::{Graph, Target},
+        parse_html,
+    };
+
+    #[test]
+    fn test_graph() {
+        let html = r#"
+        <header itemscope itemtype="https://schema.org/Organization" itemid="urn:org:acme">
+            <span itemprop="name">ACME</span>
+            <meta itemprop="sameAs" content="https://acme.example">
+        </header>
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Anvil</span>
+            <div itemprop="manufacturer" itemscope itemid="urn:org:acme">
+                <span itemprop="name">Acme Corporation</span>
+            </div>
+            <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                <data itemprop="price" value="20">20</data>
+            </div>
+        </div>
+        <footer itemscope itemtype="https://schema.org/Corporation" itemid="urn:org:acme">
+            <span itemprop="name">ACME</span>
+            <meta itemprop="sameAs" content="https://acme.example/a">
+            <meta itemprop="sameAs" content="https://acme.example/b">
+        </footer>
+        "#;
+        let items = parse_html("", html).unwrap();
+        let graph = Graph::from_items(&items);
+        assert_eq!(graph.nodes().len(), 3);
+        assert_eq!(graph.roots(), [0, 1]);
+
+        let acme = graph.get("urn:org:acme").unwrap();
+        assert_eq!(
+            acme.itemtype,
+            [
+                "https://schema.org/Organization",
+                "https://schema.org/Corporation"
+            ]
+        );
+        let names = acme.targets("name").collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                &Target::Value(ValueType::String("ACME".into())),
+                &Target::Value(ValueType::String("Acme Corporation".into()))
+            ]
+        );
+        assert_eq!(acme.targets("sameAs").count(), 3);
+
+        let product = graph.node(graph.roots()[1]).unwrap();
+        assert_eq!(
+            product.targets("manufacturer").next(),
+            Some(&Target::Node(graph.roots()[0]))
+        );
+        let Some(&Target::Node(offer)) = product.targets("offers").next() else {
+            panic!("offers should be a node");
+        };
+        assert_eq!(
+            graph.node(offer).unwrap().itemtype,
+            ["https://schema.org/Offer"]
+        );
+
+        // only the single valued name conflicts, the footer has two sameAs
+        assert_eq!(graph.conflicts().len(), 1);
+        let conflict = &graph.conflicts()[0];
+        assert_eq!(conflict.itemid, "urn:org:acme");
+        assert_eq!(conflict.name, Name::String("name".into()));
+        assert_eq!(
+            conflict.existing,
+            Target::Value(ValueType::String("ACME".into()))
+        );
+        assert_eq!(
+            conflict.incoming,
+            Target::Value(ValueType::String("Acme Corporation".into()))
+        );
+    }
+}
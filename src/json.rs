@@ -0,0 +1,325 @@
+//! `application/microdata+json` (5.3.1 JSON) serialization of extracted items.
+//!
+//! The native serde representation of the domain types uses internal
+//! `{"type": .., "value": ..}` tagging. The functions in this module emit and
+//! read the shape described by the WHATWG spec instead:
+//!
+//! ```json
+//! {"items":[{"type":["https://schema.org/Person"],"id":"urn:x","properties":{"name":["Elizabeth"]}}]}
+//! ```
+use std::{collections::VecDeque, fmt, sync::Arc};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{MapAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+};
+use url::Url;
+
+use crate::{
+    Error,
+    domain::{ItemScope, Name, Property, ValueType},
+};
+
+/// Serializes a `parse_html` result as an `application/microdata+json` document.
+pub struct Microdata<'a>(pub &'a VecDeque<ItemScope>);
+
+pub fn to_value(items: &VecDeque<ItemScope>) -> Result<serde_json::Value, Error> {
+    Ok(serde_json::to_value(Microdata(items))?)
+}
+
+pub fn to_string(items: &VecDeque<ItemScope>) -> Result<String, Error> {
+    Ok(serde_json::to_string(&Microdata(items))?)
+}
+
+pub fn to_string_pretty(items: &VecDeque<ItemScope>) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(&Microdata(items))?)
+}
+
+/// Reads an `application/microdata+json` document back into items.
+///
+/// The format only carries strings, so every non-item value comes back as
+/// `ValueType::String`. Property names that are absolute urls become `Name::Url`.
+pub fn from_str(json: &str) -> Result<VecDeque<ItemScope>, Error> {
+    Ok(serde_json::from_str::<DocumentDe>(json)?.into_items())
+}
+
+pub fn from_value(json: serde_json::Value) -> Result<VecDeque<ItemScope>, Error> {
+    Ok(serde_json::from_value::<DocumentDe>(json)?.into_items())
+}
+
+impl Serialize for Microdata<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("items", &Items(self.0))?;
+        map.end()
+    }
+}
+
+struct Items<'a>(&'a VecDeque<ItemScope>);
+struct Item<'a>(&'a ItemScope);
+struct Properties<'a>(&'a VecDeque<Property>);
+struct Values<'a>(&'a ValueType);
+struct Value<'a>(&'a ValueType);
+
+impl Serialize for Items<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(Item))
+    }
+}
+
+impl Serialize for Item<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if !self.0.itemtype.is_empty() {
+            map.serialize_entry("type", &self.0.itemtype)?;
+        }
+        if let Some(id) = &self.0.itemid {
+            map.serialize_entry("id", id)?;
+        }
+        map.serialize_entry("properties", &Properties(&self.0.items))?;
+        map.end()
+    }
+}
+
+impl Serialize for Properties<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for property in self.0 {
+            let name = match &property.name {
+                Name::Url(name) | Name::String(name) => name,
+            };
+            map.serialize_entry(name, &Values(&property.value))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Values<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            ValueType::Array(values) => serializer.collect_seq(values.iter().map(Value)),
+            value => {
+                let mut seq = serializer.serialize_seq(Some(1))?;
+                seq.serialize_element(&Value(value))?;
+                seq.end()
+            }
+        }
+    }
+}
+
+impl Serialize for Value<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            ValueType::Empty => serializer.serialize_str(""),
+            ValueType::Url(s) | ValueType::String(s) => serializer.serialize_str(s),
+            ValueType::Time(t) => serializer.serialize_str(t.lexical()),
+            ValueType::Meter(n) | ValueType::Number(n) => serializer.serialize_str(n.lexical()),
+            ValueType::ScopeRef(item_scope) => Item(item_scope).serialize(serializer),
+            array @ ValueType::Array(_) => Values(array).serialize(serializer),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DocumentDe {
+    items: Vec<ItemDe>,
+}
+
+#[derive(Deserialize)]
+struct ItemDe {
+    #[serde(rename = "type", default)]
+    itemtype: Vec<String>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    properties: PropertiesDe,
+}
+
+/// Keeps the properties in document order, which a map would not.
+#[derive(Default)]
+struct PropertiesDe(Vec<(String, Vec<ValueDe>)>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ValueDe {
+    String(String),
+    Item(ItemDe),
+}
+
+impl<'de> Deserialize<'de> for PropertiesDe {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PropertiesVisitor;
+        impl<'de> Visitor<'de> for PropertiesVisitor {
+            type Value = PropertiesDe;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of property names to arrays of values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut properties = Vec::with_capacity(map.size_hint().unwrap_or_default());
+                while let Some(entry) = map.next_entry::<String, Vec<ValueDe>>()? {
+                    properties.push(entry);
+                }
+                Ok(PropertiesDe(properties))
+            }
+        }
+        deserializer.deserialize_map(PropertiesVisitor)
+    }
+}
+
+impl DocumentDe {
+    fn into_items(self) -> VecDeque<ItemScope> {
+        self.items
+            .into_iter()
+            .map(ItemDe::into_item_scope)
+            .collect()
+    }
+}
+
+impl ItemDe {
+    fn into_item_scope(self) -> ItemScope {
+        let items = self
+            .properties
+            .0
+            .into_iter()
+            .map(|(name, values)| {
+                let name = if Url::parse(&name).is_ok() {
+                    Name::Url(name)
+                } else {
+                    Name::String(name)
+                };
+                let mut values = values
+                    .into_iter()
+                    .map(ValueDe::into_value_type)
+                    .collect::<VecDeque<_>>();
+                let value = match values.len() {
+                    0 => ValueType::Empty,
+                    1 => values.pop_front().unwrap_or(ValueType::Empty),
+                    _ => ValueType::Array(values),
+                };
+                Property::new(name, value)
+            })
+            .collect();
+        ItemScope {
+            itemid: self.id,
+            itemtype: self.itemtype,
+            items,
+            ..Default::default()
+        }
+    }
+}
+
+impl ValueDe {
+    fn into_value_type(self) -> ValueType {
+        match self {
+            ValueDe::String(s) => ValueType::String(s),
+            ValueDe::Item(item) => ValueType::ScopeRef(Arc::new(item.into_item_scope())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::VecDeque, sync::Arc};
+
+    use serde_json::json;
+
+    use crate::{
+        domain::{ItemScope, Name, Property, ValueType},
+        json, parse_html,
+    };
+
+    #[test]
+    fn test_to_json() {
+        let html = r#"
+            <div itemscope itemtype="http://schema.org/Product" itemid="urn:product:1">
+                <span itemprop="name">Panasonic White 60L Refrigerator</span>
+                <div itemprop="aggregateRating"
+                    itemscope itemtype="http://schema.org/AggregateRating">
+                    <meter itemprop="ratingValue" min=0 value=3.5 max=5>Rated 3.5/5</meter>
+                </div>
+                <p itemprop="color">white</p>
+                <p itemprop="color">grey</p>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        assert_eq!(
+            json::to_value(&res).unwrap(),
+            json!({
+                "items": [{
+                    "type": ["http://schema.org/Product"],
+                    "id": "urn:product:1",
+                    "properties": {
+                        "name": ["Panasonic White 60L Refrigerator"],
+                        "aggregateRating": [{
+                            "type": ["http://schema.org/AggregateRating"],
+                            "properties": {"ratingValue": ["3.5"]}
+                        }],
+                        "color": ["white", "grey"]
+                    }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let res = json::from_str(
+            r#"{"items":[{"properties":{"http://bittich.be/name":["Amanda"],
+                "band":[{"type":["http://schema.org/MusicGroup"],"properties":{"size":["12"]}}],
+                "flavor":["Lemon sorbet","Apricot sorbet"]}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            VecDeque::from([ItemScope {
+                itemid: None,
+                itemtype: vec![],
+                items: VecDeque::from([
+                    Property::new(
+                        Name::Url("http://bittich.be/name".into()),
+                        ValueType::String("Amanda".into())
+                    ),
+                    Property::new(
+                        Name::String("band".into()),
+                        ValueType::ScopeRef(Arc::new(ItemScope {
+                            itemid: None,
+                            itemtype: vec!["http://schema.org/MusicGroup".into()],
+                            items: VecDeque::from([Property::new(
+                                Name::String("size".into()),
+                                ValueType::String("12".into())
+                            )]),
+                            ..Default::default()
+                        }))
+                    ),
+                    Property::new(
+                        Name::String("flavor".into()),
+                        ValueType::Array(VecDeque::from([
+                            ValueType::String("Lemon sorbet".into()),
+                            ValueType::String("Apricot sorbet".into())
+                        ]))
+                    ),
+                ]),
+                ..Default::default()
+            }])
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let html = r#"
+        <div itemscope id="amanda" itemref="a b"></div>
+        <p id="a">Name: <span itemprop="name">Amanda</span></p>
+        <div id="b" itemprop="band" itemscope itemref="c"></div>
+        <div id="c">
+        <p>Band: <span itemprop="name">Jazz Band</span></p>
+        <p>Size: <span itemprop="size">12</span> players</p>
+        </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let json = json::to_string(&res).unwrap();
+        assert_eq!(json::from_str(&json).unwrap(), res);
+    }
+}
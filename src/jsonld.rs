@@ -0,0 +1,253 @@
+//! JSON-LD output of extracted items.
+//!
+//! When every `itemtype` in the result belongs to schema.org, the document is
+//! compacted against the schema.org context, so `"https://schema.org/Person"`
+//! becomes `"Person"` and `https://schema.org/name` becomes `"name"`.
+use std::collections::VecDeque;
+
+use serde_json::{Map, Value, json};
+
+use crate::{
+    Error,
+    domain::{ItemScope, Name, Numeric, ValueType},
+    rdf::{XSD_NS, vocabulary_of},
+};
+
+pub const SCHEMA_ORG_CONTEXT: &str = "https://schema.org";
+const SCHEMA_ORG_VOCABULARIES: [&str; 2] = ["https://schema.org/", "http://schema.org/"];
+
+pub fn to_jsonld(items: &VecDeque<ItemScope>) -> Value {
+    let compact = all_schema_org(items);
+    let mut nodes = items
+        .iter()
+        .map(|item| node(item, None, compact))
+        .collect::<Vec<_>>();
+    let mut document = if nodes.len() == 1 {
+        nodes.pop().unwrap_or_default()
+    } else {
+        json!({ "@graph": nodes })
+    };
+    if let Value::Object(document) = &mut document
+        && compact
+    {
+        document.insert("@context".into(), SCHEMA_ORG_CONTEXT.into());
+    }
+    document
+}
+
+pub fn to_string(items: &VecDeque<ItemScope>) -> Result<String, Error> {
+    Ok(serde_json::to_string(&to_jsonld(items))?)
+}
+
+pub fn to_string_pretty(items: &VecDeque<ItemScope>) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(&to_jsonld(items))?)
+}
+
+fn all_schema_org(items: &VecDeque<ItemScope>) -> bool {
+    fn visit<'a>(item: &'a ItemScope, types: &mut Vec<&'a str>) {
+        types.extend(item.itemtype.iter().map(|t| t.as_str()));
+        for property in &item.items {
+            visit_value(&property.value, types);
+        }
+    }
+    fn visit_value<'a>(value: &'a ValueType, types: &mut Vec<&'a str>) {
+        match value {
+            ValueType::ScopeRef(item) => visit(item, types),
+            ValueType::Array(values) => values.iter().for_each(|v| visit_value(v, types)),
+            _ => {}
+        }
+    }
+    let mut types = vec![];
+    for item in items {
+        visit(item, &mut types);
+    }
+    !types.is_empty() && types.iter().all(|t| schema_org_term(t).is_some())
+}
+
+fn schema_org_term(iri: &str) -> Option<&str> {
+    SCHEMA_ORG_VOCABULARIES
+        .iter()
+        .find_map(|vocabulary| iri.strip_prefix(vocabulary))
+        .filter(|term| !term.is_empty() && !term.contains(['/', '#']))
+}
+
+fn compact_iri(iri: &str, compact: bool) -> &str {
+    compact
+        .then(|| schema_org_term(iri))
+        .flatten()
+        .unwrap_or(iri)
+}
+
+fn node(item: &ItemScope, vocabulary: Option<&str>, compact: bool) -> Value {
+    let mut node = Map::new();
+    if let Some(itemid) = &item.itemid {
+        node.insert("@id".into(), itemid.as_str().into());
+    }
+    match item.itemtype.as_slice() {
+        [] => {}
+        [itemtype] => {
+            node.insert("@type".into(), compact_iri(itemtype, compact).into());
+        }
+        itemtypes => {
+            node.insert(
+                "@type".into(),
+                itemtypes
+                    .iter()
+                    .map(|t| compact_iri(t, compact))
+                    .collect::<Vec<_>>()
+                    .into(),
+            );
+        }
+    }
+    let vocabulary = item
+        .itemtype
+        .first()
+        .map(|t| vocabulary_of(t))
+        .or(vocabulary);
+    for property in &item.items {
+        let key = match &property.name {
+            Name::Url(url) => compact_iri(url, compact).to_string(),
+            Name::String(name) if compact => name.clone(),
+            Name::String(name) => match vocabulary {
+                Some(vocabulary) => format!("{vocabulary}{name}"),
+                None => name.clone(),
+            },
+        };
+        let value = value(&property.value, vocabulary, compact);
+        // a name given both as an url and as a plain name ends up under one key
+        match node.get_mut(&key) {
+            Some(existing) => merge(existing, value),
+            None => {
+                node.insert(key, value);
+            }
+        }
+    }
+    Value::Object(node)
+}
+
+fn merge(existing: &mut Value, value: Value) {
+    let mut values = match existing.take() {
+        Value::Array(values) => values,
+        first => vec![first],
+    };
+    match value {
+        Value::Array(more) => values.extend(more),
+        value => values.push(value),
+    }
+    *existing = Value::Array(values);
+}
+
+fn value(value_type: &ValueType, vocabulary: Option<&str>, compact: bool) -> Value {
+    match value_type {
+        ValueType::Empty => "".into(),
+        ValueType::Array(values) => values
+            .iter()
+            .map(|v| value(v, vocabulary, compact))
+            .collect::<Vec<_>>()
+            .into(),
+        ValueType::Url(url) => json!({ "@id": url }),
+        ValueType::String(s) => s.as_str().into(),
+        ValueType::Meter(n) | ValueType::Number(n) => match n.value() {
+            Numeric::Integer(i) => i.into(),
+            Numeric::Float(f) => f.into(),
+        },
+        ValueType::Time(t) => match t.kind().xsd_datatype() {
+            Some(datatype) => {
+                json!({ "@value": t.lexical(), "@type": format!("{XSD_NS}{datatype}") })
+            }
+            None => t.lexical().into(),
+        },
+        ValueType::ScopeRef(item) => node(item, vocabulary, compact),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{jsonld, parse_html};
+
+    #[test]
+    fn test_jsonld_schema_org() {
+        let html = r#"
+            <div itemscope itemtype="https://schema.org/Product" itemid="urn:product:1">
+                <span itemprop="name">Panasonic White 60L Refrigerator</span>
+                <img itemprop="image" src="https://bittich.be/fridge.jpg" alt="">
+                <div itemprop="aggregateRating"
+                    itemscope itemtype="https://schema.org/AggregateRating">
+                    <meter itemprop="ratingValue" min=0 value=3.5 max=5>Rated 3.5/5</meter>
+                    (based on <span itemprop="reviewCount">11</span> customer reviews)
+                </div>
+                <time itemprop="releaseDate" datetime="2009-05-10">May 10th 2009</time>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        assert_eq!(
+            jsonld::to_jsonld(&res),
+            json!({
+                "@context": "https://schema.org",
+                "@id": "urn:product:1",
+                "@type": "Product",
+                "name": "Panasonic White 60L Refrigerator",
+                "image": {"@id": "https://bittich.be/fridge.jpg"},
+                "aggregateRating": {
+                    "@type": "AggregateRating",
+                    "ratingValue": 3.5,
+                    "reviewCount": "11"
+                },
+                "releaseDate": {
+                    "@value": "2009-05-10",
+                    "@type": "http://www.w3.org/2001/XMLSchema#date"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_jsonld_other_vocabulary() {
+        let html = r#"
+            <div itemscope itemtype="http://microformats.org/profile/hcard">
+                <span itemprop="fn">Jack Bauer</span>
+            </div>
+            <div itemscope itemtype="https://schema.org/Person">
+                <span itemprop="name">Alice</span>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        assert_eq!(
+            jsonld::to_jsonld(&res),
+            json!({
+                "@graph": [
+                    {
+                        "@type": "http://microformats.org/profile/hcard",
+                        "http://microformats.org/profile/fn": "Jack Bauer"
+                    },
+                    {
+                        "@type": "https://schema.org/Person",
+                        "https://schema.org/name": "Alice"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_jsonld_colliding_names() {
+        let html = r#"
+            <div itemscope itemtype="https://schema.org/Person">
+                <span itemprop="https://schema.org/name">Alice</span>
+                <span itemprop="name">Alicia</span>
+                <span itemprop="name">Ali</span>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        assert_eq!(
+            jsonld::to_jsonld(&res),
+            json!({
+                "@context": "https://schema.org",
+                "@type": "Person",
+                "name": ["Alice", "Alicia", "Ali"]
+            })
+        );
+    }
+}
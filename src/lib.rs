@@ -1,236 +1,530 @@
 use std::{
-    collections::{BTreeSet, VecDeque},
-    error::Error,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    mem,
     sync::Arc,
 };
 
-use domain::{Config, ItemScope, Name, Property, ValueType};
+use datetime::Time;
+use domain::{
+    Config, Extraction, ItemScope, Name, Number, Origin, ParserOptions, Property, ValueType,
+    ValueTyping,
+};
+use ego_tree::NodeId;
 use log::debug;
 use scraper::{ElementRef, Html, Selector};
+use source::Lines;
 use url::Url;
 
+pub mod dataset;
+pub mod datetime;
+pub mod de;
+pub mod diff;
 pub mod domain;
+mod error;
+pub mod graph;
+pub mod json;
+pub mod jsonld;
+pub mod profile;
+pub mod query;
+pub mod rdf;
+pub mod render;
+pub mod schema;
+mod source;
+pub mod visit;
+
+pub use de::from_item;
+pub use error::{Error, ErrorKind, Warning};
+
+pub fn parse_html<'a>(base_url: &'a str, html: &'a str) -> Result<VecDeque<ItemScope>, Error> {
+    parse_html_with(&ParserOptions::new().base_url(base_url), html).map(|e| e.items)
+}
+
+/// Like `parse_html`, but invalid properties, broken itemrefs and cycles are
+/// skipped and reported as warnings instead of failing the whole page.
+pub fn parse_html_lenient(base_url: &str, html: &str) -> Extraction {
+    let options = ParserOptions::new().base_url(base_url).lenient(true);
+    // in lenient mode errors are turned into warnings as they happen, one that
+    // still comes through is reported as a warning about the whole document
+    match parse_html_with(&options, html) {
+        Ok(extraction) => extraction,
+        Err(e) => {
+            debug!("giving up on the document: {e}");
+            Extraction {
+                items: VecDeque::new(),
+                warnings: vec![Warning {
+                    kind: e.kind(),
+                    message: e.to_string(),
+                    location: String::new(),
+                }],
+            }
+        }
+    }
+}
 
-pub fn parse_html<'a>(
-    base_url: &'a str,
-    html: &'a str,
-) -> Result<VecDeque<ItemScope>, Box<dyn Error>> {
-    let base_url = if base_url.ends_with("/") {
-        &base_url[0..base_url.len() - 1]
+pub fn parse_html_with(options: &ParserOptions, html: &str) -> Result<Extraction, Error> {
+    // only origins need the lines
+    let (document, lines) = if options.origins {
+        source::parse_document(html)
     } else {
-        base_url
+        (Html::parse_document(html), Lines::default())
+    };
+    let base_url = document_base_url(&options.base_url, &document);
+    let config = options.config(base_url.as_ref());
+    let mut state = State {
+        lines,
+        segments: path_segments(&document),
+        ..Default::default()
+    };
+
+    let elements = document
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .collect::<Vec<_>>();
+    state.tree_order = elements
+        .iter()
+        .enumerate()
+        .map(|(idx, element_ref)| (element_ref.id(), idx))
+        .collect();
+    // itemref points to the first element in tree order with the given id
+    for element_ref in &elements {
+        if let Some(id) = element_ref.attr("id") {
+            state.ids.entry(id).or_insert(*element_ref);
+        }
+    }
+    // 5.2.2 a top-level microdata item is an item whose element has no itemprop attribute
+    for element_ref in elements.iter().filter(|e| e.attr("itemscope").is_some()) {
+        if element_ref.attr("itemprop").is_none()
+            && let Some(item) = item_scope(config, element_ref, &mut state, &mut vec![])?
+        {
+            state.items.push_back(item);
+        }
+    }
+    // an item with an itemprop that no other item crawled is not part of the result
+    for element_ref in elements.iter().filter(|e| e.attr("itemscope").is_some()) {
+        if let Some(itemprop) = element_ref.attr("itemprop")
+            && !state.properties.contains(&element_ref.id())
+        {
+            let orphan = Error::UnattachedItem {
+                itemprop: itemprop.trim().to_string(),
+                element_id: element_ref.attr("id").map(String::from),
+            };
+            push_warning(&mut state, element_ref, &orphan);
+        }
+    }
+    Ok(Extraction {
+        items: state.items,
+        warnings: state.warnings,
+    })
+}
+
+#[derive(Default)]
+struct State<'a> {
+    items: VecDeque<ItemScope>,
+    warnings: Vec<Warning>,
+    // kind and location of the warnings, as itemref can crawl an element twice
+    warned: HashSet<(ErrorKind, String)>,
+    tree_order: HashMap<NodeId, usize>,
+    lines: Lines,
+    segments: HashMap<NodeId, String>,
+    ids: HashMap<&'a str, ElementRef<'a>>,
+    // elements that were crawled as the property of an item
+    properties: HashSet<NodeId>,
+}
+
+// the first base element with an href, resolved against the given base url, wins over it
+fn document_base_url(base_url: &str, document: &Html) -> Option<Url> {
+    let base_url = Some(base_url.trim())
+        .filter(|b| !b.is_empty())
+        .and_then(|b| {
+            Url::parse(b)
+                .inspect_err(|e| debug!("could not parse base url {b}: {e}"))
+                .ok()
+        });
+    let selector = Selector::parse("base[href]").expect("valid selector");
+    let Some(href) = document
+        .select(&selector)
+        .next()
+        .and_then(|base| base.attr("href"))
+        .map(|href| href.trim())
+    else {
+        return base_url;
     };
-    let mut items = VecDeque::new();
-    let document = scraper::Html::parse_document(html);
-
-    traverse(
-        Config { base_url },
-        &document,
-        &document.root_element(),
-        &mut None,
-        &mut items,
-        &mut BTreeSet::new(),
-    )?;
-    Ok(items)
+    let resolved = match &base_url {
+        Some(base_url) => base_url.join(href),
+        None => Url::parse(href),
+    };
+    resolved
+        .inspect_err(|e| debug!("could not resolve base element href {href}: {e}"))
+        .ok()
+        .or(base_url)
+}
+
+// resolved against the base url when there is one, kept as written otherwise
+fn serialize_itemid(config: Config, itemid: &str) -> String {
+    let itemid = itemid.trim();
+    match config.base_url.map(|base_url| base_url.join(itemid)) {
+        Some(Ok(url)) => url.to_string(),
+        Some(Err(e)) => {
+            debug!("cannot resolve itemid {itemid}: {e}");
+            itemid.to_string()
+        }
+        None => itemid.to_string(),
+    }
 }
 
 // 5.2.4 Values
 fn serialize_url<'a>(config: Config<'a>, url_elt: Option<&'a str>) -> ValueType {
-    if let Some(url_elt) = url_elt {
-        match url::Url::parse(url_elt.trim()) {
+    let Some(url_elt) = url_elt.map(|u| u.trim()) else {
+        return ValueType::Empty;
+    };
+    match config.base_url {
+        Some(base_url) => base_url
+            .join(url_elt)
+            .inspect_err(|e| debug!("cannot resolve url {url_elt} against {base_url}: {e}"))
+            .map(|u| ValueType::Url(u.to_string()))
+            .unwrap_or(ValueType::Empty),
+        None => match Url::parse(url_elt) {
             Ok(url) => ValueType::Url(url.to_string()),
-            Err(_) if config.base_url.is_empty() => ValueType::String(url_elt.trim().to_string()), // assume relative
-            Err(e) => {
-                debug!("could not parse url {e}");
-                let url_elt = if url_elt.starts_with("/") {
-                    &url_elt[0..url_elt.len() - 1]
-                } else {
-                    url_elt
-                };
-                let absolute_url = format!("{}/{url_elt}", config.base_url);
-                Url::parse(&absolute_url)
-                    .inspect_err(|e| debug!("still cannot parse url even with a base! {e}"))
-                    .ok()
-                    .map(|u| ValueType::Url(u.to_string()))
-                    .unwrap_or(ValueType::Empty)
+            Err(_) => ValueType::String(url_elt.to_string()), // assume relative
+        },
+    }
+}
+
+fn property_value<'a>(
+    config: Config<'a>,
+    element_ref: &ElementRef<'a>,
+) -> Result<ValueType, Error> {
+    let name = element_ref.value().name();
+    let value =
+        match name {
+            "time" if config.value_typing == ValueTyping::Text => {
+                ValueType::String(datetime_value(element_ref))
+            }
+            "meter" | "data" if config.value_typing == ValueTyping::Text => element_ref
+                .attr("value")
+                .map(|s| ValueType::String(s.trim().into()))
+                .unwrap_or(ValueType::Empty),
+            "meta" if config.normalize_whitespace => element_ref
+                .attr("content")
+                .map(|s| ValueType::String(collapse_whitespace(s)))
+                .unwrap_or(ValueType::Empty),
+            "meta" => element_ref
+                .attr("content")
+                .map(|s| ValueType::String(s.into()))
+                .unwrap_or(ValueType::Empty),
+            "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => {
+                serialize_url(config, element_ref.attr("src"))
             }
+            "a" | "area" | "link" => serialize_url(config, element_ref.attr("href")),
+            "object" => serialize_url(config, element_ref.attr("data")),
+            "data" => element_ref
+                .attr("value")
+                .map(|s| {
+                    Number::parse(s.trim())
+                        .map(ValueType::Number)
+                        .unwrap_or_else(|| ValueType::String(s.trim().into()))
+                })
+                .unwrap_or(ValueType::Empty),
+            "meter" => match element_ref.attr("value").map(|s| s.trim()) {
+                Some(value) => Number::parse(value).map(ValueType::Meter).ok_or_else(|| {
+                    Error::InvalidNumber {
+                        value: value.to_string(),
+                        element_id: element_ref.attr("id").map(String::from),
+                    }
+                })?,
+                None => ValueType::Empty,
+            },
+            "time" => {
+                let datetime = datetime_value(element_ref);
+                Time::parse(&datetime)
+                    .map(ValueType::Time)
+                    .unwrap_or(ValueType::String(datetime))
+            }
+            _ if config.normalize_whitespace => {
+                ValueType::String(collapse_whitespace(&element_ref.text().collect::<String>()))
+            }
+            _ => ValueType::String(
+                element_ref
+                    .text()
+                    .filter(|t| !t.trim().is_empty())
+                    .map(|t| t.trim().to_string())
+                    .collect::<Vec<_>>()
+                    .join(""),
+            ),
+        };
+    Ok(value)
+}
+
+// the datetime attribute, or else the element's text content
+fn datetime_value(element_ref: &ElementRef) -> String {
+    match element_ref.attr("datetime") {
+        Some(datetime) => datetime.trim().to_string(),
+        None => element_ref.text().collect::<String>().trim().to_string(),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// in lenient mode the error is recorded as a warning and None is returned
+fn recover<T>(
+    config: Config,
+    state: &mut State<'_>,
+    element_ref: &ElementRef,
+    result: Result<T, Error>,
+) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if config.lenient => {
+            debug!("skipping: {e}");
+            push_warning(state, element_ref, &e);
+            Ok(None)
         }
-    } else {
-        ValueType::Empty
+        Err(e) => Err(e),
     }
 }
 
-fn property_value<'a>(config: Config<'a>, element_ref: &ElementRef<'a>) -> ValueType {
-    match element_ref.value().name() {
-        "meta" => element_ref
-            .attr("content")
-            .map(|s| ValueType::String(s.into()))
-            .unwrap_or(ValueType::Empty),
-        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => {
-            serialize_url(config, element_ref.attr("src"))
+fn origin(config: Config, state: &State<'_>, element_ref: &ElementRef) -> Origin {
+    if !config.origins {
+        return Origin::default();
+    }
+    Origin {
+        position: state.tree_order.get(&element_ref.id()).copied(),
+        path: Some(element_path(&state.segments, element_ref)),
+        line: state.lines.get(&element_ref.id()).copied(),
+    }
+}
+
+fn push_warning(state: &mut State<'_>, element_ref: &ElementRef, e: &Error) {
+    let location = element_path(&state.segments, element_ref);
+    if state.warned.insert((e.kind(), location.clone())) {
+        state.warnings.push(Warning {
+            kind: e.kind(),
+            message: e.to_string(),
+            location,
+        });
+    }
+}
+
+// the last segment of the css-like path of every element: div#amanda, p:nth-of-type(2)...
+fn path_segments(document: &Html) -> HashMap<NodeId, String> {
+    let mut segments = HashMap::new();
+    for parent in document.tree.root().descendants() {
+        let mut by_name = HashMap::<_, Vec<_>>::new();
+        for child in parent.children().filter_map(ElementRef::wrap) {
+            by_name.entry(child.value().name()).or_default().push(child);
+        }
+        for (name, same_name) in by_name {
+            for (idx, element) in same_name.iter().enumerate() {
+                let segment = match element.attr("id") {
+                    Some(id) => format!("{name}#{id}"),
+                    None if same_name.len() > 1 => format!("{name}:nth-of-type({})", idx + 1),
+                    None => name.to_string(),
+                };
+                segments.insert(element.id(), segment);
+            }
         }
-        "a" | "area" | "link" => serialize_url(config, element_ref.attr("href")),
-        "object" => serialize_url(config, element_ref.attr("data")),
-        "data" => element_ref
-            .attr("value")
-            .map(|s| ValueType::String(s.trim().into()))
-            .unwrap_or(ValueType::Empty),
-        "meter" => element_ref
-            .attr("value")
-            .map(|s| ValueType::Meter(s.trim().into())) // todo it's a numeric type
-            .unwrap_or(ValueType::Empty),
-        "time" => element_ref
-            .attr("datetime")
-            .map(|s| ValueType::Time(s.trim().into())) // todo it's a datetime type
-            .unwrap_or(ValueType::Empty),
-        _ => ValueType::String(
-            element_ref
-                .text()
-                .filter(|t| !t.trim().is_empty())
-                .map(|t| t.trim().to_string())
-                .collect::<Vec<_>>()
-                .join(""),
-        ),
     }
+    segments
+}
+
+// css-like path of an element, e.g. html > body > div#amanda > p:nth-of-type(2)
+fn element_path(segments: &HashMap<NodeId, String>, element_ref: &ElementRef) -> String {
+    let mut path = std::iter::once(**element_ref)
+        .chain(element_ref.ancestors())
+        .filter_map(|node| segments.get(&node.id()).map(String::as_str))
+        .collect::<Vec<_>>();
+    path.reverse();
+    path.join(" > ")
 }
 
-fn serialize_name(config: Config, itemprop: String) -> Result<Name, Box<dyn Error>> {
-    match serialize_url(config, Some(itemprop.as_str())) {
-        ValueType::Url(url) => Ok(domain::Name::Url(url)),
-        _ if !itemprop
-            .chars()
-            .any(|b| ['\u{003A}', '\u{002E}'].contains(&b)) =>
+fn serialize_name(element_ref: &ElementRef, itemprop: String) -> Result<Name, Error> {
+    match Url::parse(&itemprop) {
+        Ok(url) => Ok(domain::Name::Url(url.to_string())),
+        Err(_)
+            if !itemprop
+                .chars()
+                .any(|b| ['\u{003A}', '\u{002E}'].contains(&b)) =>
         {
-            Ok(domain::Name::String(itemprop.to_string()))
+            Ok(domain::Name::String(itemprop))
         }
-        _ => Err(format!("itemprop {itemprop} is not a valid property").into()),
+        Err(_) => Err(Error::InvalidPropertyName {
+            itemprop,
+            element_id: element_ref.attr("id").map(String::from),
+        }),
     }
 }
-fn traverse<'a>(
+fn tokens(attr: Option<&str>) -> impl Iterator<Item = &str> {
+    attr.unwrap_or_default().split_ascii_whitespace()
+}
+
+// ancestors are the elements of the items being built, outermost first
+fn item_scope<'a>(
     config: Config<'a>,
-    document: &'a Html,
     element_ref: &ElementRef<'a>,
-    parent: &mut Option<&mut VecDeque<Property>>,
-    items: &mut VecDeque<ItemScope>,
-    in_ref: &mut BTreeSet<Option<&'a str>>,
-) -> Result<(), Box<dyn Error>> {
-    let itemscope = element_ref.attr("itemscope");
-    let itemid = element_ref.attr("itemid").map(|r| r.trim().to_string());
-    let itemtype = element_ref
-        .attr("itemtype")
-        .map(|r| {
-            r.split(" ")
-                .map(|r| r.trim().to_string())
-                .filter(|r| !r.is_empty())
-                .filter(|r| Url::parse(r).is_ok())
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_default();
-    let itemrefs = element_ref.attr("itemref").map(|r| {
-        r.split(" ")
-            .map(|r| r.trim().to_string())
-            .filter(|r| !r.is_empty())
-            .collect::<Vec<_>>()
-    });
-    let mut itemprops = element_ref.attr("itemprop").map(|r| {
-        r.split(" ")
-            .map(|r| r.trim().to_string())
-            .filter(|r| !r.is_empty())
-            .collect::<Vec<_>>()
-    });
-    if itemscope.is_some() {
-        let mut itemscope = ItemScope {
-            itemtype,
-            itemid,
-            ..Default::default()
+    state: &mut State<'a>,
+    ancestors: &mut Vec<ElementRef<'a>>,
+) -> Result<Option<ItemScope>, Error> {
+    if let Some(max) = config.max_depth.filter(|max| ancestors.len() >= *max) {
+        let limit = Error::LimitExceeded {
+            limit: "max_depth",
+            max,
+            element_id: element_ref.attr("id").map(String::from),
         };
-        if let Some(itemrefs) = itemrefs {
-            if itemrefs.iter().any(|s| in_ref.contains(&Some(s))) {
-                return Err(format!("cycle detected! {in_ref:?}").into());
-            }
-            for itemref in itemrefs {
-                let selector =
-                    Selector::parse(&format!("#{itemref}")).map_err(|e| e.to_string())?;
-                let elts = document.select(&selector);
-                for elt in elts {
-                    if elt.attr("id").is_some() {
-                        in_ref.insert(elt.attr("id"));
-                    }
-                    traverse(
-                        config,
-                        document,
-                        &elt,
-                        &mut Some(&mut itemscope.items),
-                        items,
-                        in_ref,
-                    )?;
-                    in_ref.remove(&elt.attr("id"));
-                }
+        return recover(config, state, element_ref, Err(limit));
+    }
+    let mut item = ItemScope {
+        itemid: element_ref
+            .attr("itemid")
+            .map(|itemid| serialize_itemid(config, itemid)),
+        itemtype: tokens(element_ref.attr("itemtype"))
+            .filter(|r| config.keep_invalid_itemtypes || Url::parse(r).is_ok())
+            .map(String::from)
+            .collect(),
+        origin: origin(config, state, element_ref),
+        ..Default::default()
+    };
+    let mut properties = Properties::default();
+    ancestors.push(*element_ref);
+    for (property, itemref) in item_properties(config, element_ref, state, ancestors)? {
+        state.properties.insert(property.id());
+        let mut names = vec![];
+        for itemprop in tokens(property.attr("itemprop")) {
+            let name = serialize_name(&property, itemprop.to_string());
+            if let Some(name) = recover(config, state, &property, name)? {
+                names.push(name);
             }
         }
-        for child in element_ref.child_elements() {
-            traverse(
-                config,
-                document,
-                &child,
-                &mut Some(&mut itemscope.items),
-                items,
-                in_ref,
-            )?;
+        if names.is_empty() {
+            continue;
         }
-        if let Some(itemprops) = itemprops.take() {
-            let itemscope = Arc::new(itemscope);
-            for itemprop in itemprops {
-                if let Some(parent) = parent.as_deref_mut() {
-                    let name = serialize_name(config, itemprop)?;
-                    let value = domain::ValueType::ScopeRef(itemscope.clone());
-                    // let prop = Property { name, value };
-                    if let Some(prop_with_same_name) = parent.iter_mut().find(|p| p.name == name) {
-                        match &mut prop_with_same_name.value {
-                            ValueType::Array(value_types) => value_types.push_back(value),
-                            _ => {
-                                prop_with_same_name.value = ValueType::Array(VecDeque::from([
-                                    prop_with_same_name.value.clone(),
-                                    value,
-                                ]));
-                            }
-                        }
-                    } else {
-                        parent.push_back(Property { name, value });
-                    }
-                }
-            }
+        let value = if property.attr("itemscope").is_none() {
+            let value = property_value(config, &property);
+            recover(config, state, &property, value)?
+        } else if ancestors.contains(&property) {
+            let cycle = Error::CycleDetected {
+                itemref: itemref.unwrap_or_default().to_string(),
+                element_id: element_ref.attr("id").map(String::from),
+                ids: cycle_ids(ancestors, itemref),
+            };
+            recover(config, state, element_ref, Err(cycle))?
         } else {
-            items.push_back(itemscope);
+            item_scope(config, &property, state, ancestors)?
+                .map(|item| ValueType::ScopeRef(Arc::new(item)))
+        };
+        let Some(value) = value else {
+            continue;
+        };
+        let origin = origin(config, state, &property);
+        for name in names {
+            properties.add(name, value.clone(), &origin);
+        }
+    }
+    ancestors.pop();
+    item.items = properties.items;
+    Ok(Some(item))
+}
+
+// 5.2.5 the properties of an item: the property elements found crawling the
+// children of root and the elements it references, in tree order. Each one comes
+// with the itemref token it was reached through, if any.
+fn item_properties<'a>(
+    config: Config<'a>,
+    root: &ElementRef<'a>,
+    state: &mut State<'a>,
+    ancestors: &[ElementRef<'a>],
+) -> Result<Vec<(ElementRef<'a>, Option<&'a str>)>, Error> {
+    let mut memory = HashSet::from([root.id()]);
+    let mut pending = root
+        .child_elements()
+        .map(|child| (child, None))
+        .collect::<Vec<_>>();
+    for itemref in tokens(root.attr("itemref")) {
+        let referenced = state
+            .ids
+            .get(itemref)
+            .copied()
+            .ok_or_else(|| Error::InvalidItemRef {
+                itemref: itemref.to_string(),
+                element_id: root.attr("id").map(String::from),
+                reason: "no element with this id".into(),
+            });
+        if let Some(referenced) = recover(config, state, root, referenced)? {
+            pending.push((referenced, Some(itemref)));
         }
-    } else if let Some(itemprops) = itemprops.take() {
-        for itemprop in itemprops {
-            if let Some(parent) = parent.as_deref_mut() {
-                let name = serialize_name(config, itemprop)?;
-                let value = property_value(config, element_ref);
-                // let prop = Property { name, value };
-                if let Some(prop_with_same_name) = parent.iter_mut().find(|p| p.name == name) {
-                    match &mut prop_with_same_name.value {
-                        ValueType::Array(value_types) => value_types.push_back(value),
-                        _ => {
-                            prop_with_same_name.value = ValueType::Array(VecDeque::from([
-                                prop_with_same_name.value.clone(),
-                                value,
-                            ]));
-                        }
+    }
+    let mut results = vec![];
+    while let Some((candidate, itemref)) = pending.pop() {
+        if !memory.insert(candidate.id()) {
+            if candidate == *root {
+                let cycle = Error::CycleDetected {
+                    itemref: itemref.unwrap_or_default().to_string(),
+                    element_id: root.attr("id").map(String::from),
+                    ids: cycle_ids(ancestors, itemref),
+                };
+                recover::<()>(config, state, root, Err(cycle))?;
+            } else {
+                debug!(
+                    "{} crawled more than once",
+                    element_path(&state.segments, &candidate)
+                );
+            }
+            continue;
+        }
+        if candidate.attr("itemscope").is_none() {
+            pending.extend(candidate.child_elements().map(|child| (child, itemref)));
+        }
+        if tokens(candidate.attr("itemprop")).next().is_some() {
+            results.push((candidate, itemref));
+        }
+    }
+    results.sort_by_key(|(element_ref, _)| state.tree_order.get(&element_ref.id()).copied());
+    Ok(results)
+}
+
+// ids of the items being built, plus the itemref that leads back to one of them
+fn cycle_ids(ancestors: &[ElementRef], itemref: Option<&str>) -> BTreeSet<String> {
+    ancestors
+        .iter()
+        .filter_map(|ancestor| ancestor.attr("id"))
+        .chain(itemref)
+        .map(String::from)
+        .collect()
+}
+
+// properties of an item being built, in the order their names first appear
+#[derive(Default)]
+struct Properties {
+    items: VecDeque<Property>,
+    by_name: HashMap<Name, usize>,
+}
+
+impl Properties {
+    // a name seen again adds to the values of its first property, which keeps its origin
+    fn add(&mut self, name: Name, value: ValueType, origin: &Origin) {
+        match self.by_name.get(&name) {
+            Some(&idx) => {
+                let existing = &mut self.items[idx].value;
+                match existing {
+                    ValueType::Array(values) => values.push_back(value),
+                    _ => {
+                        let first = mem::replace(existing, ValueType::Empty);
+                        *existing = ValueType::Array(VecDeque::from([first, value]));
                     }
-                } else {
-                    parent.push_back(Property { name, value });
                 }
             }
-        }
-    } else {
-        for child in element_ref.child_elements() {
-            // check what's next
-            traverse(config, document, &child, parent, items, in_ref)?;
+            None => {
+                self.by_name.insert(name.clone(), self.items.len());
+                self.items.push_back(Property {
+                    name,
+                    value,
+                    origin: origin.clone(),
+                });
+            }
         }
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -241,8 +535,12 @@ mod test {
     };
 
     use crate::{
-        domain::{ItemScope, Name, Property, ValueType},
-        parse_html,
+        Error, ErrorKind, Warning,
+        datetime::{Time, TimeKind},
+        domain::{
+            ItemScope, Name, Number, Numeric, ParserOptions, Property, ValueType, ValueTyping,
+        },
+        parse_html, parse_html_lenient, parse_html_with,
     };
 
     #[test]
@@ -251,20 +549,22 @@ mod test {
             ItemScope {
                 itemtype: vec![],
                 itemid: None,
-                items: vec![Property {
-                    name: Name::String("name".to_string()),
-                    value: ValueType::String("Elizabeth".to_string()),
-                }]
+                items: vec![Property::new(
+                    Name::String("name".to_string()),
+                    ValueType::String("Elizabeth".to_string()),
+                )]
                 .into(),
+                ..Default::default()
             },
             ItemScope {
                 itemid: None,
                 itemtype: vec![],
-                items: vec![Property {
-                    name: Name::String("name".to_string()),
-                    value: ValueType::String("Daniel".to_string()),
-                }]
+                items: vec![Property::new(
+                    Name::String("name".to_string()),
+                    ValueType::String("Daniel".to_string()),
+                )]
                 .into(),
+                ..Default::default()
             },
         ]
         .into_iter()
@@ -310,19 +610,20 @@ mod test {
                 itemid: None,
                 itemtype: vec![],
                 items: VecDeque::from([
-                    Property {
-                        name: Name::String("name".to_string()),
-                        value: ValueType::String("Neil".into())
-                    },
-                    Property {
-                        name: Name::String("band".to_string()),
-                        value: ValueType::String("Four Parts Water".into())
-                    },
-                    Property {
-                        name: Name::String("nationality".to_string()),
-                        value: ValueType::String("British".into())
-                    },
-                ])
+                    Property::new(
+                        Name::String("name".to_string()),
+                        ValueType::String("Neil".into())
+                    ),
+                    Property::new(
+                        Name::String("band".to_string()),
+                        ValueType::String("Four Parts Water".into())
+                    ),
+                    Property::new(
+                        Name::String("nationality".to_string()),
+                        ValueType::String("British".into())
+                    ),
+                ]),
+                ..Default::default()
             }])
         );
     }
@@ -340,10 +641,11 @@ mod test {
             VecDeque::from([ItemScope {
                 itemid: None,
                 itemtype: vec![],
-                items: VecDeque::from([Property {
-                    name: Name::Url("http://bittich.be/image".to_string()),
-                    value: ValueType::Url("http://bittich.be/google-logo.png".into())
-                }])
+                items: VecDeque::from([Property::new(
+                    Name::String("image".to_string()),
+                    ValueType::Url("http://bittich.be/google-logo.png".into())
+                )]),
+                ..Default::default()
             }])
         );
     }
@@ -361,10 +663,11 @@ mod test {
             VecDeque::from([ItemScope {
                 itemid: None,
                 itemtype: vec![],
-                items: VecDeque::from([Property {
-                    name: Name::Url("http://bittich.be/product-id".to_string()),
-                    value: ValueType::String("9678AOU879".into())
-                }])
+                items: VecDeque::from([Property::new(
+                    Name::String("product-id".to_string()),
+                    ValueType::String("9678AOU879".into())
+                )]),
+                ..Default::default()
             }])
         );
     }
@@ -389,29 +692,31 @@ mod test {
                 itemid: None,
                 itemtype: vec!["http://schema.org/Product".into()],
                 items: VecDeque::from([
-                    Property {
-                        name: Name::Url("http://bittich.be/name".to_string()),
-                        value: ValueType::String("Panasonic White 60L Refrigerator".into())
-                    },
-                    Property {
-                        name: Name::Url("http://bittich.be/aggregateRating".to_string()),
-                        value: ValueType::ScopeRef(Arc::new(ItemScope {
+                    Property::new(
+                        Name::String("name".to_string()),
+                        ValueType::String("Panasonic White 60L Refrigerator".into())
+                    ),
+                    Property::new(
+                        Name::String("aggregateRating".to_string()),
+                        ValueType::ScopeRef(Arc::new(ItemScope {
                             itemtype: vec!["http://schema.org/AggregateRating".into()],
                             itemid: None,
                             items: vec![
-                                Property {
-                                    name: Name::Url("http://bittich.be/ratingValue".to_string()),
-                                    value: ValueType::Meter("3.5".into())
-                                },
-                                Property {
-                                    name: Name::Url("http://bittich.be/reviewCount".to_string()),
-                                    value: ValueType::String("11".into())
-                                },
+                                Property::new(
+                                    Name::String("ratingValue".to_string()),
+                                    ValueType::Meter(Number::parse("3.5").unwrap())
+                                ),
+                                Property::new(
+                                    Name::String("reviewCount".to_string()),
+                                    ValueType::String("11".into())
+                                ),
                             ]
-                            .into()
+                            .into(),
+                            ..Default::default()
                         }))
-                    },
-                ])
+                    ),
+                ]),
+                ..Default::default()
             }])
         );
     }
@@ -428,10 +733,11 @@ mod test {
             VecDeque::from([ItemScope {
                 itemid: None,
                 itemtype: vec![],
-                items: VecDeque::from([Property {
-                    name: Name::Url("http://bittich.be/birthday".to_string()),
-                    value: ValueType::Time("2009-05-10".into())
-                }])
+                items: VecDeque::from([Property::new(
+                    Name::String("birthday".to_string()),
+                    ValueType::Time(Time::parse("2009-05-10").unwrap())
+                )]),
+                ..Default::default()
             }])
         );
     }
@@ -450,29 +756,31 @@ mod test {
                 itemid: None,
                 itemtype: vec![],
                 items: VecDeque::from([
-                    Property {
-                        name: Name::Url("http://bittich.be/name".to_string()),
-                        value: ValueType::String("Amanda".into())
-                    },
-                    Property {
-                        name: Name::Url("http://bittich.be/band".to_string()),
-                        value: ValueType::ScopeRef(Arc::new(ItemScope {
+                    Property::new(
+                        Name::String("name".to_string()),
+                        ValueType::String("Amanda".into())
+                    ),
+                    Property::new(
+                        Name::String("band".to_string()),
+                        ValueType::ScopeRef(Arc::new(ItemScope {
                             itemtype: vec![],
                             itemid: None,
                             items: vec![
-                                Property {
-                                    name: Name::Url("http://bittich.be/name".to_string()),
-                                    value: ValueType::String("Jazz Band".into())
-                                },
-                                Property {
-                                    name: Name::Url("http://bittich.be/size".to_string()),
-                                    value: ValueType::String("12".into())
-                                },
+                                Property::new(
+                                    Name::String("name".to_string()),
+                                    ValueType::String("Jazz Band".into())
+                                ),
+                                Property::new(
+                                    Name::String("size".to_string()),
+                                    ValueType::String("12".into())
+                                ),
                             ]
-                            .into()
+                            .into(),
+                            ..Default::default()
                         }))
-                    },
-                ])
+                    ),
+                ]),
+                ..Default::default()
             }])
         );
     }
@@ -495,29 +803,31 @@ mod test {
                 itemid: None,
                 itemtype: vec![],
                 items: VecDeque::from([
-                    Property {
-                        name: Name::String("name".to_string()),
-                        value: ValueType::String("Amanda".into())
-                    },
-                    Property {
-                        name: Name::String("band".to_string()),
-                        value: ValueType::ScopeRef(Arc::new(ItemScope {
+                    Property::new(
+                        Name::String("name".to_string()),
+                        ValueType::String("Amanda".into())
+                    ),
+                    Property::new(
+                        Name::String("band".to_string()),
+                        ValueType::ScopeRef(Arc::new(ItemScope {
                             itemtype: vec![],
                             itemid: None,
                             items: vec![
-                                Property {
-                                    name: Name::String("name".to_string()),
-                                    value: ValueType::String("Jazz Band".into())
-                                },
-                                Property {
-                                    name: Name::String("size".to_string()),
-                                    value: ValueType::String("12".into())
-                                },
+                                Property::new(
+                                    Name::String("name".to_string()),
+                                    ValueType::String("Jazz Band".into())
+                                ),
+                                Property::new(
+                                    Name::String("size".to_string()),
+                                    ValueType::String("12".into())
+                                ),
                             ]
-                            .into()
+                            .into(),
+                            ..Default::default()
                         }))
-                    },
-                ])
+                    ),
+                ]),
+                ..Default::default()
             }])
         );
     }
@@ -539,16 +849,17 @@ mod test {
             VecDeque::from([ItemScope {
                 itemid: None,
                 itemtype: vec![],
-                items: VecDeque::from([Property {
-                    name: Name::Url("http://bittich.be/flavor".to_string()),
-                    value: ValueType::Array(
+                items: VecDeque::from([Property::new(
+                    Name::String("flavor".to_string()),
+                    ValueType::Array(
                         [
                             ValueType::String("Lemon sorbet".into()),
                             ValueType::String("Apricot sorbet".into())
                         ]
                         .into()
                     )
-                }])
+                )]),
+                ..Default::default()
             }])
         );
     }
@@ -567,15 +878,16 @@ mod test {
                 itemid: None,
                 itemtype: vec![],
                 items: VecDeque::from([
-                    Property {
-                        name: Name::Url("http://bittich.be/favorite-color".to_string()),
-                        value: ValueType::String("orange".into())
-                    },
-                    Property {
-                        name: Name::Url("http://bittich.be/favorite-fruit".to_string()),
-                        value: ValueType::String("orange".into())
-                    },
-                ])
+                    Property::new(
+                        Name::String("favorite-color".to_string()),
+                        ValueType::String("orange".into())
+                    ),
+                    Property::new(
+                        Name::String("favorite-fruit".to_string()),
+                        ValueType::String("orange".into())
+                    ),
+                ]),
+                ..Default::default()
             }])
         );
     }
@@ -593,10 +905,11 @@ mod test {
             VecDeque::from([ItemScope {
                 itemid: None,
                 itemtype: vec![],
-                items: VecDeque::from([Property {
-                    name: Name::Url("http://bittich.be/name".to_string()),
-                    value: ValueType::String("The Castle".into())
-                },])
+                items: VecDeque::from([Property::new(
+                    Name::String("name".to_string()),
+                    ValueType::String("The Castle".into())
+                ),]),
+                ..Default::default()
             }])
         );
         let html = r#"
@@ -611,6 +924,7 @@ mod test {
     }
     #[test]
     fn test_example12() {
+        // itemref pointing to an item without itemprop does not make it a property
         let html = r#"
         <div itemscope id="amanda" itemref="a b"></div>
         <p id="a">Name: <span itemprop="name">Amanda</span></p>
@@ -621,13 +935,41 @@ mod test {
         <p>Size: <span itemprop="size">12</span> players</p>
         </div>
         "#;
-        let res = parse_html("http://bittich.be/", html);
+        let res = parse_html("", html).unwrap();
         assert_eq!(
-            res.err().map(|e| e.to_string()),
-            Some(format!(
-                "cycle detected! {:?}",
-                BTreeSet::from([Some("amanda"), Some("b"), Some("c")])
-            ))
+            res,
+            VecDeque::from([ItemScope {
+                itemid: None,
+                itemtype: vec![],
+                items: VecDeque::from([
+                    Property::new(
+                        Name::String("name".into()),
+                        ValueType::String("Amanda".into())
+                    ),
+                    Property::new(
+                        Name::String("band".into()),
+                        ValueType::ScopeRef(Arc::new(ItemScope {
+                            itemid: None,
+                            itemtype: vec![],
+                            items: VecDeque::from([
+                                Property::new(
+                                    Name::String("name".into()),
+                                    ValueType::Array(VecDeque::from([
+                                        ValueType::String("Jazz Band".into()),
+                                        ValueType::ScopeRef(Arc::new(ItemScope::default()))
+                                    ]))
+                                ),
+                                Property::new(
+                                    Name::String("size".into()),
+                                    ValueType::String("12".into())
+                                ),
+                            ]),
+                            ..Default::default()
+                        }))
+                    ),
+                ]),
+                ..Default::default()
+            }])
         );
 
         assert_eq!(
@@ -641,103 +983,58 @@ mod test {
             )
             .err()
             .map(|s| s.to_string()),
-            Some(format!(
-                "cycle detected! {:?}",
-                BTreeSet::from([Some("person1")])
-            ))
+            Some(format!("cycle detected! {:?}", BTreeSet::from(["person1"])))
         );
 
-        assert_eq!(
-            parse_html(
-                "http://bittich.be",
-                r#"
-        <div itemscope itemtype="http://schema.org/Person" id="person1" itemref="person2">
-        <span itemprop="name">Bob</span>
-        </div>
-        <div itemscope itemtype="http://schema.org/Person" id="person2" itemref="person1">
-        <span itemprop="name">Carol</span>
+        let html = r#"
+        <div itemscope id="amanda">
+            <div id="band" itemprop="band" itemscope>
+                <span itemprop="member" itemscope itemref="band"></span>
+            </div>
         </div>
-        "#
-            )
-            .err()
-            .map(|s| s.to_string()),
-            Some(format!(
-                "cycle detected! {:?}",
-                BTreeSet::from([Some("person1"), Some("person2")])
-            ))
-        );
-        assert_eq!(
-            parse_html(
-                "http://bittich.be",
-                r#"
-                <div itemscope itemtype="http://schema.org/Person" id="a" itemref="b">
-                <span itemprop="name">Dave</span>
-                </div>
-
-                <div itemscope itemtype="http://schema.org/Person" id="b" itemref="c">
-                <span itemprop="name">Eve</span>
-                </div>
-
-                <div itemscope itemtype="http://schema.org/Person" id="c" itemref="a">
-                <span itemprop="name">Frank</span>
-                </div>        
-                "#
-            )
-            .err()
-            .map(|s| s.to_string()),
-            Some(format!(
-                "cycle detected! {:?}",
-                BTreeSet::from([Some("a"), Some("b"), Some("c")])
-            ))
-        );
-        assert_eq!(
-            parse_html(
-                "http://bittich.be",
-                r#"
-                <div itemscope itemtype="http://schema.org/Organization" id="org" itemref="team leader">
-                <span itemprop="name">TechCorp</span>
-                </div>
-
-                <div itemscope itemtype="http://schema.org/Person" id="leader" itemref="org team">
-                <span itemprop="name">Grace</span>
-                </div>
+        "#;
+        assert!(matches!(
+            parse_html("http://bittich.be", html),
+            Err(Error::CycleDetected { itemref, element_id: None, ids })
+                if itemref == "band" && ids == BTreeSet::from(["amanda".into(), "band".into()])
+        ));
 
-                <div itemscope itemtype="http://schema.org/Person" id="team" itemref="leader">
-                <span itemprop="name">Heidi</span>
-                </div>     
-                "#
-            )
-            .err()
-            .map(|s| s.to_string()),
-            Some(format!(
-                "cycle detected! {:?}",
-                BTreeSet::from([Some("leader"),Some("team")])
-            ))
-        );
-        assert_eq!(
-            parse_html(
-                "http://bittich.be",
-                r#"
-                    <div itemscope itemtype="http://schema.org/Event" id="event" itemref="venue">
-                    <span itemprop="name">Conference 2025</span>
-                    </div>
-
-                    <div itemscope itemtype="http://schema.org/Place" id="venue" itemref="organizer">
-                    <span itemprop="name">City Hall</span>
-                    </div>
-
-                    <div itemscope itemtype="http://schema.org/Organization" id="organizer" itemref="event">
-                    <span itemprop="name">TechGroup</span>
-                    </div>  
-                "#
-            )
-            .err()
-            .map(|s| s.to_string()),
-            Some(format!(
-                "cycle detected! {:?}",
-                BTreeSet::from([Some("event"),Some("organizer"), Some("venue")])
-            ))
-        );
+        // top-level items referencing each other are not properties of one another
+        for html in [
+            r#"
+            <div itemscope itemtype="http://schema.org/Person" id="person1" itemref="person2">
+            <span itemprop="name">Bob</span>
+            </div>
+            <div itemscope itemtype="http://schema.org/Person" id="person2" itemref="person1">
+            <span itemprop="name">Carol</span>
+            </div>
+            "#,
+            r#"
+            <div itemscope itemtype="http://schema.org/Person" id="a" itemref="b">
+            <span itemprop="name">Dave</span>
+            </div>
+            <div itemscope itemtype="http://schema.org/Person" id="b" itemref="c">
+            <span itemprop="name">Eve</span>
+            </div>
+            <div itemscope itemtype="http://schema.org/Person" id="c" itemref="a">
+            <span itemprop="name">Frank</span>
+            </div>
+            "#,
+            r#"
+            <div itemscope itemtype="http://schema.org/Organization" id="org" itemref="team leader">
+            <span itemprop="name">TechCorp</span>
+            </div>
+            <div itemscope itemtype="http://schema.org/Person" id="leader" itemref="org team">
+            <span itemprop="name">Grace</span>
+            </div>
+            <div itemscope itemtype="http://schema.org/Person" id="team" itemref="leader">
+            <span itemprop="name">Heidi</span>
+            </div>
+            "#,
+        ] {
+            let res = parse_html("http://bittich.be", html).unwrap();
+            assert!(res.iter().all(|item| item.items.len() == 1));
+        }
     }
 
     #[test]
@@ -761,19 +1058,20 @@ mod test {
                 itemid: Some("urn:isbn:0-330-34032-8".into()),
                 itemtype: vec!["https://vocab.example.net/book".into()],
                 items: VecDeque::from([
-                    Property {
-                        name: Name::Url("http://bittich.be/title".to_string()),
-                        value: ValueType::String("The Reality Dysfunction".into())
-                    },
-                    Property {
-                        name: Name::Url("http://bittich.be/author".to_string()),
-                        value: ValueType::String("Peter F. Hamilton".into())
-                    },
-                    Property {
-                        name: Name::Url("http://bittich.be/pubdate".to_string()),
-                        value: ValueType::Time("1996-01-26".into())
-                    },
-                ])
+                    Property::new(
+                        Name::String("title".to_string()),
+                        ValueType::String("The Reality Dysfunction".into())
+                    ),
+                    Property::new(
+                        Name::String("author".to_string()),
+                        ValueType::String("Peter F. Hamilton".into())
+                    ),
+                    Property::new(
+                        Name::String("pubdate".to_string()),
+                        ValueType::Time(Time::parse("1996-01-26").unwrap())
+                    ),
+                ]),
+                ..Default::default()
             }])
         );
     }
@@ -791,6 +1089,19 @@ mod test {
             res.err().map(|s| s.to_string()),
             Some("itemprop :b is not a valid property".to_string())
         );
+        let html = r#"
+        <div itemscope itemref="1abc"></div>
+        <p id="1abc" itemprop="a">1</p>
+        "#;
+        assert_eq!(parse_html("", html).unwrap()[0].items.len(), 1);
+        let html = r#"
+        <div itemscope itemref="missing"></div>
+        <p id="1abc" itemprop="a">1</p>
+        "#;
+        assert!(matches!(
+            parse_html("", html),
+            Err(Error::InvalidItemRef { itemref, .. }) if itemref == "missing"
+        ));
     }
 
     #[test]
@@ -858,4 +1169,417 @@ mod test {
         let res = parse_html("", html).unwrap();
         println!("{}", serde_json::to_string_pretty(&res).unwrap());
     }
+
+    #[test]
+    fn test_relative_urls() {
+        let html = r##"
+            <div itemscope>
+                <img itemprop="a" src="img.png">
+                <img itemprop="b" src="../img.png">
+                <img itemprop="c" src="/img.png">
+                <a itemprop="d" href="?q=1">query</a>
+                <a itemprop="e" href="#frag">fragment</a>
+                <a itemprop="f" href="//cdn.bittich.be/x">protocol relative</a>
+                <a itemprop="g" href=" https://schema.org/Thing ">absolute</a>
+                <a itemprop="h">no href</a>
+            </div>
+        "##;
+        let res = parse_html("http://bittich.be/a/b/page.html", html).unwrap();
+        assert_eq!(
+            res[0].items.iter().map(|p| &p.value).collect::<Vec<_>>(),
+            vec![
+                &ValueType::Url("http://bittich.be/a/b/img.png".into()),
+                &ValueType::Url("http://bittich.be/a/img.png".into()),
+                &ValueType::Url("http://bittich.be/img.png".into()),
+                &ValueType::Url("http://bittich.be/a/b/page.html?q=1".into()),
+                &ValueType::Url("http://bittich.be/a/b/page.html#frag".into()),
+                &ValueType::Url("http://cdn.bittich.be/x".into()),
+                &ValueType::Url("https://schema.org/Thing".into()),
+                &ValueType::Empty,
+            ]
+        );
+        let res = parse_html("", html).unwrap();
+        assert_eq!(
+            res[0].items[1].value,
+            ValueType::String("../img.png".into())
+        );
+    }
+
+    #[test]
+    fn test_base_element() {
+        let html = r#"
+            <html>
+            <head><base href="/static/"></head>
+            <body>
+            <div itemscope itemid="p/1">
+                <img itemprop="image" src="img.png">
+            </div>
+            </body>
+            </html>
+        "#;
+        let res = parse_html("http://bittich.be/a/page.html", html).unwrap();
+        assert_eq!(
+            res[0].itemid.as_deref(),
+            Some("http://bittich.be/static/p/1")
+        );
+        assert_eq!(
+            res[0].items[0],
+            Property::new(
+                Name::String("image".into()),
+                ValueType::Url("http://bittich.be/static/img.png".into())
+            )
+        );
+        let html = html.replace("/static/", "https://cdn.bittich.be/");
+        let res = parse_html("", &html).unwrap();
+        assert_eq!(res[0].itemid.as_deref(), Some("https://cdn.bittich.be/p/1"));
+        assert_eq!(
+            res[0].items[0].value,
+            ValueType::Url("https://cdn.bittich.be/img.png".into())
+        );
+    }
+
+    #[test]
+    fn test_lenient() {
+        let html = r#"
+        <div itemscope>
+            <p itemprop="a">1</p>
+            <p itemprop=":b">test</p>
+            <p itemprop="c">2</p>
+        </div>
+        <div itemscope itemref="a 1abc"></div>
+        <div id="a">
+            <span itemprop="band" itemscope itemref="a"><span itemprop="name">Jazz Band</span></span>
+        </div>
+        "#;
+        assert!(parse_html("", html).is_err());
+        let res = parse_html_lenient("", html);
+        assert_eq!(
+            res.items,
+            VecDeque::from([
+                ItemScope {
+                    itemid: None,
+                    itemtype: vec![],
+                    items: VecDeque::from([
+                        Property::new(Name::String("a".into()), ValueType::String("1".into())),
+                        Property::new(Name::String("c".into()), ValueType::String("2".into())),
+                    ]),
+                    ..Default::default()
+                },
+                ItemScope {
+                    itemid: None,
+                    itemtype: vec![],
+                    items: VecDeque::from([Property::new(
+                        Name::String("band".into()),
+                        ValueType::ScopeRef(Arc::new(ItemScope {
+                            itemid: None,
+                            itemtype: vec![],
+                            items: VecDeque::from([Property::new(
+                                Name::String("name".into()),
+                                ValueType::String("Jazz Band".into())
+                            )]),
+                            ..Default::default()
+                        }))
+                    )]),
+                    ..Default::default()
+                }
+            ])
+        );
+        assert_eq!(
+            res.warnings,
+            [
+                Warning {
+                    kind: ErrorKind::InvalidPropertyName,
+                    message: "itemprop :b is not a valid property".into(),
+                    location: "html > body > div:nth-of-type(1) > p:nth-of-type(2)".into()
+                },
+                Warning {
+                    kind: ErrorKind::InvalidItemRef,
+                    message: "itemref 1abc is not valid: no element with this id".into(),
+                    location: "html > body > div:nth-of-type(2)".into()
+                },
+                Warning {
+                    kind: ErrorKind::CycleDetected,
+                    message: r#"cycle detected! {"a"}"#.into(),
+                    location: "html > body > div#a > span".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_options() {
+        let html = r#"
+        <div itemscope itemtype="Person http://schema.org/Person">
+            <p itemprop="name">My   <em>name</em>
+              is Neil</p>
+            <meter itemprop="rating" value=" 3.5 ">3.5</meter>
+            <div itemprop="band" itemscope>
+                <span itemprop="name">Four Parts Water</span>
+            </div>
+        </div>
+        "#;
+        let options = ParserOptions::new()
+            .keep_invalid_itemtypes(true)
+            .normalize_whitespace(true)
+            .value_typing(ValueTyping::Text);
+        let res = parse_html_with(&options, html).unwrap();
+        assert_eq!(
+            res.items[0].itemtype,
+            vec!["Person".to_string(), "http://schema.org/Person".to_string()]
+        );
+        assert_eq!(
+            res.items[0].items[0].value,
+            ValueType::String("My name is Neil".into())
+        );
+        assert_eq!(res.items[0].items[1].value, ValueType::String("3.5".into()));
+
+        let res = parse_html_with(&options.clone().max_depth(1), html);
+        assert!(matches!(
+            res,
+            Err(Error::LimitExceeded {
+                limit: "max_depth",
+                max: 1,
+                element_id: None
+            })
+        ));
+        let res = parse_html_with(&options.max_depth(1).lenient(true), html).unwrap();
+        assert_eq!(res.items[0].items.len(), 2);
+        assert_eq!(res.warnings[0].kind, ErrorKind::LimitExceeded);
+    }
+
+    #[test]
+    fn test_numeric_values() {
+        for valid in ["0", "-12", "3.5", "1e3", "-1.25E-2", "007"] {
+            assert_eq!(
+                Number::parse(valid).map(|n| n.to_string()),
+                Some(valid.into())
+            );
+        }
+        for invalid in [
+            "", "+1", ".5", "1.", "1e", "0x10", "1,5", "NaN", "Infinity", "1 2",
+        ] {
+            assert_eq!(Number::parse(invalid), None, "{invalid}");
+        }
+        assert_eq!(
+            Number::parse("-12").map(|n| n.value()),
+            Some(Numeric::Integer(-12))
+        );
+        assert_eq!(
+            Number::parse("1e3").map(|n| n.value()),
+            Some(Numeric::Float(1000.))
+        );
+
+        let html = r#"
+        <div itemscope>
+            <meter itemprop="rating" min=0 value="4" max=5>4/5</meter>
+            <data itemprop="price" value="19.99">19.99 EUR</data>
+            <data itemprop="sku" value="9678AOU879">The Instigator 2000</data>
+        </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let values = res[0].items.iter().map(|p| &p.value).collect::<Vec<_>>();
+        assert_eq!(values[0].as_i64(), Some(4));
+        assert_eq!(
+            values[1],
+            &ValueType::Number(Number::parse("19.99").unwrap())
+        );
+        assert_eq!(values[1].as_f64(), Some(19.99));
+        assert_eq!(values[1].as_i64(), None);
+        assert_eq!(values[2], &ValueType::String("9678AOU879".into()));
+        assert_eq!(values[2].as_number(), None);
+
+        let html = r#"
+        <div itemscope>
+            <meter id="m" itemprop="rating" min=0 value="four" max=5>4/5</meter>
+            <span itemprop="name">Neil</span>
+        </div>
+        "#;
+        assert!(matches!(
+            parse_html("", html),
+            Err(Error::InvalidNumber { value, element_id: Some(id) }) if value == "four" && id == "m"
+        ));
+        let res = parse_html_lenient("", html);
+        assert_eq!(res.items[0].items.len(), 1);
+        assert_eq!(res.warnings[0].kind, ErrorKind::InvalidNumber);
+
+        let html = html.replace("four", "1e400");
+        assert!(matches!(
+            parse_html("", &html),
+            Err(Error::InvalidNumber { value, .. }) if value == "1e400"
+        ));
+        assert!(Number::parse("1e300").is_some());
+    }
+
+    #[test]
+    fn test_time_values() {
+        let html = r#"
+        <div itemscope>
+            <time itemprop="published" datetime="2011-11-18T14:54:39Z">Nov 18</time>
+            <time itemprop="duration">PT4H18M</time>
+            <time itemprop="week" datetime="2011-W47">week 47</time>
+            <time itemprop="holiday" datetime="Christmas">Dec 25</time>
+        </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let values = res[0].items.iter().map(|p| &p.value).collect::<Vec<_>>();
+        assert_eq!(
+            values[0].as_time().map(Time::kind),
+            Some(TimeKind::GlobalDateTime)
+        );
+        assert_eq!(
+            values[1].as_time().map(Time::kind),
+            Some(TimeKind::Duration)
+        );
+        assert_eq!(values[1].as_str(), Some("PT4H18M"));
+        assert_eq!(values[2].as_time().map(Time::kind), Some(TimeKind::Week));
+        assert_eq!(values[3], &ValueType::String("Christmas".into()));
+
+        let options = ParserOptions::new().value_typing(ValueTyping::Text);
+        let res = parse_html_with(&options, html).unwrap();
+        assert_eq!(
+            res.items[0].items[1].value,
+            ValueType::String("PT4H18M".into())
+        );
+    }
+
+    #[test]
+    fn test_top_level_items() {
+        let html = r#"
+        <div itemscope id="root" itemref="ref ref child">
+            <p id="child" itemprop="a">1</p>
+            <div itemprop="nested" itemscope><p itemprop="b">2</p></div>
+        </div>
+        <p id="ref" itemprop="c">3</p>
+        <section itemprop="orphan" itemscope><p itemprop="d">4</p></section>
+        "#;
+        let res = parse_html_with(&ParserOptions::new(), html).unwrap();
+        assert_eq!(res.items.len(), 1);
+        let names = res.items[0]
+            .items
+            .iter()
+            .map(|p| &p.name)
+            .collect::<Vec<_>>();
+        // each element is a property once, in tree order, and nested items keep their own
+        assert_eq!(
+            names,
+            [
+                &Name::String("a".into()),
+                &Name::String("nested".into()),
+                &Name::String("c".into())
+            ]
+        );
+        assert_eq!(
+            res.warnings,
+            [Warning {
+                kind: ErrorKind::UnattachedItem,
+                message: "item with itemprop orphan is not a property of any item".into(),
+                location: "html > body > section".into()
+            }]
+        );
+
+        // an item with itemprop reached through itemref is a property, not a top-level item
+        let html = r#"
+        <div itemscope itemref="band"></div>
+        <div id="band" itemprop="band" itemscope><span itemprop="name">Jazz Band</span></div>
+        "#;
+        let res = parse_html_with(&ParserOptions::new(), html).unwrap();
+        assert_eq!(res.items.len(), 1);
+        assert_eq!(res.items[0].items.len(), 1);
+        assert!(res.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_property_order() {
+        let html = r#"
+        <div itemscope itemref="c a">
+            <p itemprop="b">2</p>
+            <p itemprop="d">4</p>
+        </div>
+        <p id="a" itemprop="a">1</p>
+        <p id="c" itemprop="c">3</p>
+        <p itemprop="a">ignored</p>
+        "#;
+        let res = parse_html_with(&ParserOptions::new().origins(true), html)
+            .unwrap()
+            .items;
+        let properties = res[0]
+            .items
+            .iter()
+            .map(|p| (p.name.clone(), p.origin.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            properties,
+            [
+                (Name::String("b".into()), Some(4)),
+                (Name::String("d".into()), Some(5)),
+                (Name::String("a".into()), Some(6)),
+                (Name::String("c".into()), Some(7)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_origin() {
+        let html = r#"<html><body>
+        <div itemscope id="amanda">
+            <p>Name: <span itemprop="name">Amanda</span></p>
+            <div itemprop="band" itemscope
+                itemtype="https://schema.org/MusicGroup">
+            </div>
+        </div>
+        </body></html>"#;
+        let res = parse_html_with(&ParserOptions::new().origins(true), html)
+            .unwrap()
+            .items;
+        assert_eq!(
+            res[0].origin.path.as_deref(),
+            Some("html > body > div#amanda")
+        );
+        assert_eq!(res[0].origin.line, Some(2));
+        let name = &res[0].items[0];
+        assert_eq!(
+            name.origin.path.as_deref(),
+            Some("html > body > div#amanda > p > span")
+        );
+        assert_eq!(name.origin.line, Some(3));
+        // a start tag spanning several lines is reported on the line it starts
+        let ValueType::ScopeRef(band) = &res[0].items[1].value else {
+            panic!("band is an item");
+        };
+        assert_eq!(band.origin.line, Some(4));
+        assert_eq!(res[0].items[1].origin.line, Some(4));
+        let multiline = "<div itemscope>\n<p\n  itemprop=\"a\"\n  title=\"x\ny\">1</p><span\nitemprop=\"b\">2</span>\n</div>";
+        let res = parse_html_with(&ParserOptions::new().origins(true), multiline).unwrap();
+        let lines = res.items[0]
+            .items
+            .iter()
+            .map(|p| p.origin.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [Some(2), Some(5)]);
+
+        // origins are only recorded when asked for, and left out of the json
+        let res = parse_html("", html).unwrap();
+        assert!(res[0].origin.is_empty() && res[0].items[0].origin.is_empty());
+        let json = serde_json::to_value(&res).unwrap();
+        assert!(json[0].get("origin").is_none());
+        assert!(json[0]["items"][0].get("origin").is_none());
+    }
+
+    #[test]
+    fn test_itemref_ids() {
+        let html = r#"
+        <div itemscope itemref="a.b 1abc dup"></div>
+        <p id="a.b" itemprop="a">1</p>
+        <p id="1abc" itemprop="b">2</p>
+        <p id="dup" itemprop="c">3</p>
+        <p id="dup" itemprop="d">4</p>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let names = res[0]
+            .items
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"].map(|name| Name::String(name.into())));
+    }
 }
@@ -0,0 +1,298 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use log::{error, warn};
+use microdata::{
+    domain::{Extraction, ParserOptions},
+    json, jsonld, rdf,
+};
+
+const USAGE: &str = "\
+Extract microdata from html documents.
+
+Usage: microdata [OPTIONS] [PATH]...
+
+Reads stdin when no path (or -) is given. Directories are searched
+recursively for .html and .htm files. With several files, the json
+formats print one object keyed by path and ntriples prefixes blank
+node labels with the index of the file (_:d1b0).
+
+Options:
+  -b, --base-url <URL>   url relative urls are resolved against
+  -f, --format <FORMAT>  native, whatwg, jsonld or ntriples [default: native]
+      --lenient          report problems as warnings instead of failing
+  -h, --help             print this help";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    #[default]
+    Native,
+    Whatwg,
+    JsonLd,
+    NTriples,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Args {
+    base_url: Option<String>,
+    format: Format,
+    lenient: bool,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-b" | "--base-url" => {
+                parsed.base_url = Some(args.next().ok_or("--base-url expects a url")?);
+            }
+            "-f" | "--format" => {
+                parsed.format = match args.next().as_deref() {
+                    Some("native") => Format::Native,
+                    Some("whatwg") => Format::Whatwg,
+                    Some("jsonld") => Format::JsonLd,
+                    Some("ntriples") => Format::NTriples,
+                    Some(other) => return Err(format!("unknown format {other}")),
+                    None => return Err("--format expects a format".into()),
+                };
+            }
+            "--lenient" => parsed.lenient = true,
+            "-" => parsed.paths.push(PathBuf::from("-")),
+            option if option.starts_with('-') => return Err(format!("unknown option {option}")),
+            path => parsed.paths.push(PathBuf::from(path)),
+        }
+    }
+    if parsed.paths.is_empty() {
+        parsed.paths.push(PathBuf::from("-"));
+    }
+    Ok(Some(parsed))
+}
+
+// the html files of a directory, sorted so the output is stable
+fn html_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            html_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// pages are not always valid utf-8, invalid sequences become U+FFFD
+fn read(path: &Path) -> io::Result<String> {
+    let bytes = if path == Path::new("-") {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(path)?
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+enum Output {
+    Json(serde_json::Value),
+    Triples(Vec<rdf::Triple>),
+}
+
+fn render(format: Format, extraction: &Extraction) -> Result<Output, microdata::Error> {
+    let items = &extraction.items;
+    match format {
+        Format::Native => Ok(Output::Json(serde_json::to_value(items)?)),
+        Format::Whatwg => Ok(Output::Json(json::to_value(items)?)),
+        Format::JsonLd => Ok(Output::Json(jsonld::to_jsonld(items))),
+        Format::NTriples => Ok(Output::Triples(rdf::to_triples(items))),
+    }
+}
+
+// blank node labels restart in every document, prefix them with its index
+fn relabel(triples: &mut [rdf::Triple], document: usize) {
+    for triple in triples {
+        for term in [&mut triple.subject, &mut triple.object] {
+            if let rdf::Term::BlankNode(label) = term {
+                *label = format!("d{document}{label}");
+            }
+        }
+    }
+}
+
+// None when the document could not be read or parsed
+fn extract(args: &Args, path: &Path) -> Option<Output> {
+    let html = match read(path) {
+        Ok(html) => html,
+        Err(e) => {
+            error!("{}: {e}", path.display());
+            return None;
+        }
+    };
+    let options = ParserOptions::new()
+        .base_url(args.base_url.clone().unwrap_or_default())
+        .lenient(args.lenient);
+    let output = microdata::parse_html_with(&options, html.as_str()).and_then(|extraction| {
+        for warning in &extraction.warnings {
+            warn!(
+                "{}: {} at {}",
+                path.display(),
+                warning.message,
+                warning.location
+            );
+        }
+        render(args.format, &extraction)
+    });
+    output
+        .inspect_err(|e| error!("{}: {e}", path.display()))
+        .ok()
+}
+
+fn print_json(value: &serde_json::Value) -> bool {
+    match serde_json::to_string_pretty(value) {
+        Ok(output) => {
+            println!("{output}");
+            true
+        }
+        Err(e) => {
+            error!("{e}");
+            false
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut paths = vec![];
+    for path in &args.paths {
+        if path.is_dir() {
+            if let Err(e) = html_files(path, &mut paths) {
+                error!("{}: {e}", path.display());
+                return ExitCode::from(2);
+            }
+        } else {
+            paths.push(path.clone());
+        }
+    }
+    // several documents make a single json object keyed by path
+    let several = paths.len() > 1;
+    let mut documents = serde_json::Map::new();
+    let mut success = true;
+    for (document, path) in paths.iter().enumerate() {
+        match extract(&args, path) {
+            Some(Output::Json(value)) if several => {
+                documents.insert(path.display().to_string(), value);
+            }
+            Some(Output::Json(value)) => success &= print_json(&value),
+            Some(Output::Triples(mut triples)) => {
+                if several {
+                    relabel(&mut triples, document);
+                }
+                print!("{}", rdf::to_ntriples(&triples));
+            }
+            None => success = false,
+        }
+    }
+    if several && args.format != Format::NTriples {
+        success &= print_json(&serde_json::Value::Object(documents));
+    }
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use microdata::rdf::{self, Term};
+
+    use crate::{Args, Format, parse_args, read, relabel};
+
+    fn args(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            args(&[]),
+            Ok(Some(Args {
+                paths: vec![PathBuf::from("-")],
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            args(&[
+                "-f",
+                "jsonld",
+                "--base-url",
+                "http://bittich.be/",
+                "--lenient",
+                "a.html",
+                "pages"
+            ]),
+            Ok(Some(Args {
+                base_url: Some("http://bittich.be/".into()),
+                format: Format::JsonLd,
+                lenient: true,
+                paths: vec![PathBuf::from("a.html"), PathBuf::from("pages")],
+            }))
+        );
+        assert_eq!(args(&["--help"]), Ok(None));
+        assert!(args(&["--format", "xml"]).is_err());
+        assert!(args(&["--base-url"]).is_err());
+        assert!(args(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_relabel() {
+        let items = microdata::parse_html(
+            "",
+            r#"<div itemscope itemtype="https://schema.org/Person"><span itemprop="name">Ada</span></div>"#,
+        )
+        .unwrap();
+        let mut triples = rdf::to_triples(&items);
+        relabel(&mut triples, 1);
+        assert!(
+            triples
+                .iter()
+                .all(|triple| triple.subject == Term::BlankNode("d1b0".into()))
+        );
+    }
+
+    #[test]
+    fn test_read_latin1() {
+        let path =
+            std::env::temp_dir().join(format!("microdata-latin1-{}.html", std::process::id()));
+        fs::write(&path, b"<span itemprop=\"name\">Caf\xe9</span>").unwrap();
+        let html = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(html, "<span itemprop=\"name\">Caf\u{FFFD}</span>");
+    }
+}
@@ -0,0 +1,379 @@
+//! Required and recommended properties of rich results, e.g. Google's
+//! `Product` or `Recipe`.
+//!
+//! ```
+//! use microdata::profile::Profiles;
+//!
+//! let html = r#"
+//!     <div itemscope itemtype="https://schema.org/Product">
+//!         <span itemprop="name">Fridge</span>
+//!         <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+//!             <data itemprop="price" value="499">499</data>
+//!         </div>
+//!     </div>
+//! "#;
+//! let items = microdata::parse_html("", html).unwrap();
+//! let reports = Profiles::defaults().evaluate(&items);
+//! assert_eq!(reports.len(), 1);
+//! assert!(reports[0].is_eligible());
+//! assert!(reports[0].missing_recommended.contains(&"offers.priceCurrency".to_string()));
+//! ```
+//!
+//! Profiles are declared in JSON or TOML, the built-in ones being
+//! `src/schema/profiles.toml`:
+//!
+//! ```toml
+//! [[profiles]]
+//! name = "Product"
+//! itemtype = "Product"
+//! required = ["name", "offers|review|aggregateRating"]
+//! recommended = ["offers.price"]
+//! ```
+//!
+//! A profile applies to the items, top level or nested, whose schema.org type
+//! is its `itemtype` or one of its subtypes. A requirement is a dotted path of
+//! property names, met when the path leads to at least one non-empty value;
+//! `|` separates alternatives. Paths of the reports follow the validator's
+//! (`[0].offers`).
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::Path,
+    sync::LazyLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error,
+    domain::{ItemScope, ValueType},
+    schema::{Vocabulary, schema_org_name},
+};
+
+const DEFAULT_PROFILES: &str = include_str!("schema/profiles.toml");
+
+static DEFAULTS: LazyLock<Profiles> = LazyLock::new(|| {
+    Profiles::from_toml(DEFAULT_PROFILES).expect("the built-in profiles are valid")
+});
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Profile {
+    pub name: String,
+    /// schema.org type name, e.g. `Product`
+    pub itemtype: String,
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub recommended: Vec<String>,
+}
+
+/// How an item does against a profile.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub path: String,
+    pub profile: String,
+    pub missing_required: Vec<String>,
+    pub missing_recommended: Vec<String>,
+}
+
+impl Report {
+    pub fn is_eligible(&self) -> bool {
+        self.missing_required.is_empty()
+    }
+}
+
+impl Profiles {
+    /// The built-in profiles: Product, Recipe, Event, JobPosting, FAQPage and
+    /// BreadcrumbList.
+    pub fn defaults() -> &'static Profiles {
+        &DEFAULTS
+    }
+
+    pub fn from_json(json: &str) -> Result<Profiles, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Profiles, Error> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Reads a `.toml` file as TOML and any other file as JSON.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Profiles, Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+        {
+            Profiles::from_toml(&content)
+        } else {
+            Profiles::from_json(&content)
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// A report per item and profile that applies to it, depth first, subtypes
+    /// being those of the bundled schema.org vocabulary.
+    pub fn evaluate(&self, items: &VecDeque<ItemScope>) -> Vec<Report> {
+        self.evaluate_with(Vocabulary::schema_org(), items)
+    }
+
+    /// Like `evaluate`, with the subtypes of the given vocabulary, e.g. one
+    /// loaded from a newer schema.org release.
+    pub fn evaluate_with(
+        &self,
+        vocabulary: &Vocabulary,
+        items: &VecDeque<ItemScope>,
+    ) -> Vec<Report> {
+        let mut reports = vec![];
+        for (i, item) in items.iter().enumerate() {
+            self.evaluate_item(vocabulary, &format!("[{i}]"), item, &mut reports);
+        }
+        reports
+    }
+
+    fn evaluate_item(
+        &self,
+        vocabulary: &Vocabulary,
+        path: &str,
+        item: &ItemScope,
+        reports: &mut Vec<Report>,
+    ) {
+        let types = item
+            .itemtype
+            .iter()
+            .filter_map(|itemtype| schema_org_name(itemtype))
+            .flat_map(|name| vocabulary.ancestors(name))
+            .collect::<HashSet<_>>();
+        for profile in &self.profiles {
+            if types.contains(profile.itemtype.as_str()) {
+                reports.push(profile.evaluate(path, item));
+            }
+        }
+        for property in &item.items {
+            let path = format!("{path}.{}", property.name.as_str());
+            match &property.value {
+                ValueType::ScopeRef(nested) => {
+                    self.evaluate_item(vocabulary, &path, nested, reports)
+                }
+                ValueType::Array(values) => {
+                    for (i, value) in values.iter().enumerate() {
+                        if let ValueType::ScopeRef(nested) = value {
+                            self.evaluate_item(
+                                vocabulary,
+                                &format!("{path}[{i}]"),
+                                nested,
+                                reports,
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Profile {
+    fn evaluate(&self, path: &str, item: &ItemScope) -> Report {
+        let missing = |requirements: &[String]| {
+            requirements
+                .iter()
+                .filter(|requirement| !is_met(item, requirement))
+                .cloned()
+                .collect()
+        };
+        Report {
+            path: path.to_string(),
+            profile: self.name.clone(),
+            missing_required: missing(&self.required),
+            missing_recommended: missing(&self.recommended),
+        }
+    }
+}
+
+fn is_met(item: &ItemScope, requirement: &str) -> bool {
+    requirement.split('|').any(|path| {
+        item.path(path.trim()).any(|value| match value {
+            ValueType::Empty => false,
+            ValueType::String(s) => !s.trim().is_empty(),
+            _ => true,
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io};
+
+    use crate::{
+        Error, parse_html,
+        profile::{Profiles, Report},
+        schema::Vocabulary,
+    };
+
+    #[test]
+    fn test_defaults() {
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/Recipe">
+            <span itemprop="name">Waffles</span>
+            <meta itemprop="recipeIngredient" content="flour">
+        </div>
+        <div itemscope itemtype="https://schema.org/FAQPage">
+            <div itemprop="mainEntity" itemscope itemtype="https://schema.org/Question">
+                <span itemprop="name">Why?</span>
+                <div itemprop="acceptedAnswer" itemscope itemtype="https://schema.org/Answer">
+                    <span itemprop="text">Because.</span>
+                </div>
+            </div>
+        </div>
+        <div itemscope itemtype="https://schema.org/ItemList">
+            <div itemprop="itemListElement" itemscope itemtype="https://schema.org/Product">
+                <span itemprop="name">Fridge</span>
+                <div itemprop="review" itemscope itemtype="https://schema.org/Review"></div>
+            </div>
+            <div itemprop="itemListElement" itemscope itemtype="https://schema.org/Product">
+                <span itemprop="name"> </span>
+            </div>
+        </div>
+        <div itemscope itemtype="https://schema.org/MusicEvent">
+            <span itemprop="name">Concert</span>
+        </div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        let reports = Profiles::defaults().evaluate(&items);
+        let summary = reports
+            .iter()
+            .map(|report| {
+                (
+                    report.path.as_str(),
+                    report.profile.as_str(),
+                    report.missing_required.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("[0]", "Recipe", vec!["image".to_string()]),
+                ("[1]", "FAQPage", vec![]),
+                ("[2].itemListElement[0]", "Product", vec![]),
+                (
+                    "[2].itemListElement[1]",
+                    "Product",
+                    vec![
+                        "name".to_string(),
+                        "offers|review|aggregateRating".to_string()
+                    ]
+                ),
+                (
+                    "[3]",
+                    "Event",
+                    vec!["startDate".to_string(), "location".to_string()]
+                ),
+            ]
+        );
+        assert!(reports[1].is_eligible());
+        // a subtype of Event gets the Event profile
+        assert!(!reports[4].is_eligible());
+        assert!(
+            reports[0]
+                .missing_recommended
+                .contains(&"recipeInstructions".to_string())
+        );
+        assert!(
+            !reports[0]
+                .missing_recommended
+                .contains(&"recipeIngredient".to_string())
+        );
+    }
+
+    #[test]
+    fn test_custom_profiles() {
+        let json = r#"{"profiles": [
+            {"name": "Place", "itemtype": "Place", "required": ["address.postalCode"]}
+        ]}"#;
+        let toml = r#"
+            [[profiles]]
+            name = "Place"
+            itemtype = "Place"
+            required = ["address.postalCode"]
+        "#;
+        let profiles = Profiles::from_json(json).unwrap();
+        assert_eq!(Profiles::from_toml(toml).unwrap(), profiles);
+        assert!(profiles.get("Place").unwrap().recommended.is_empty());
+
+        let dir = std::env::temp_dir().join(format!("microdata-profiles-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("profiles.toml"), toml).unwrap();
+        fs::write(dir.join("profiles.json"), json).unwrap();
+        assert_eq!(
+            Profiles::from_file(dir.join("profiles.toml")).unwrap(),
+            profiles
+        );
+        assert_eq!(
+            Profiles::from_file(dir.join("profiles.json")).unwrap(),
+            profiles
+        );
+        let missing = Profiles::from_file(dir.join("missing.toml")).unwrap_err();
+        assert!(matches!(&missing, Error::Io(e) if e.kind() == io::ErrorKind::NotFound));
+        assert!(std::error::Error::source(&missing).is_some());
+        assert!(matches!(Profiles::from_json("{"), Err(Error::Json(_))));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // a restaurant is a place, through local business
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/Restaurant">
+            <div itemprop="address" itemscope itemtype="https://schema.org/PostalAddress">
+                <span itemprop="postalCode">1000</span>
+            </div>
+        </div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        assert_eq!(
+            profiles.evaluate(&items),
+            [Report {
+                path: "[0]".into(),
+                profile: "Place".into(),
+                missing_required: vec![],
+                missing_recommended: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_with() {
+        let profiles = Profiles::from_json(
+            r#"{"profiles": [{"name": "Place", "itemtype": "Place", "required": ["name"]}]}"#,
+        )
+        .unwrap();
+        let vocabulary = Vocabulary::from_json(
+            r#"{"types": {"Thing": [], "Place": ["Thing"], "Pitch": ["Place"]}, "properties": {}}"#,
+        )
+        .unwrap();
+        let items = parse_html(
+            "",
+            r#"<div itemscope itemtype="https://schema.org/Pitch"><span itemprop="name">A12</span></div>"#,
+        )
+        .unwrap();
+        assert!(profiles.evaluate(&items).is_empty());
+        assert_eq!(
+            profiles.evaluate_with(&vocabulary, &items),
+            [Report {
+                path: "[0]".into(),
+                profile: "Place".into(),
+                missing_required: vec![],
+                missing_recommended: vec![],
+            }]
+        );
+    }
+}
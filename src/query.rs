@@ -0,0 +1,314 @@
+//! Navigation helpers over parsed items.
+//!
+//! ```
+//! use microdata::query::Query;
+//!
+//! let html = r#"
+//!     <div itemscope itemtype="https://schema.org/Product">
+//!         <span itemprop="name">Fridge</span>
+//!         <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+//!             <data itemprop="price" value="499">499 €</data>
+//!         </div>
+//!     </div>
+//! "#;
+//! let items = microdata::parse_html("", html).unwrap();
+//! let prices = items
+//!     .items_of_type("https://schema.org/Product")
+//!     .flat_map(|product| product.path("offers.price"))
+//!     .filter_map(|price| price.as_i64())
+//!     .collect::<Vec<_>>();
+//! assert_eq!(prices, [499]);
+//! ```
+//!
+//! Property names are matched against the full name or its local name, so
+//! `name` finds `https://schema.org/name`. Multi-valued properties yield each
+//! of their values. Names holding a `.`, like absolute urls, are reached with
+//! `path_of` and a slice of names instead of a dotted path.
+use std::collections::VecDeque;
+
+use crate::domain::{ItemScope, Name, ValueType};
+
+impl ItemScope {
+    pub fn has_type(&self, itemtype: &str) -> bool {
+        self.itemtype.iter().any(|t| t == itemtype)
+    }
+
+    /// Values of the properties called `name`.
+    pub fn values<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a ValueType> {
+        self.items
+            .iter()
+            .filter(move |p| p.name.as_str() == name || p.name.local_name() == name)
+            .flat_map(|p| flatten(&p.value))
+    }
+
+    pub fn value(&self, name: &str) -> Option<&ValueType> {
+        self.values(name).next()
+    }
+
+    /// Values at the end of a dotted path of property names, e.g.
+    /// `offers.price`, following nested items.
+    pub fn path<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a ValueType> + 'a {
+        path_values(self, path.split('.'))
+    }
+
+    /// Like `path`, with the names given one by one, e.g.
+    /// `["offers", "https://schema.org/price"]`.
+    pub fn path_of<'a>(&'a self, names: &'a [&'a str]) -> impl Iterator<Item = &'a ValueType> + 'a {
+        path_values(self, names.iter().copied())
+    }
+
+    /// This item and the items nested in it, depth first in document order.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            stack: vec![Nested {
+                item: self,
+                depth: 0,
+                name: None,
+                parent: None,
+            }],
+        }
+    }
+
+    /// Like `walk`, without the position of each item.
+    pub fn descendants(&self) -> impl Iterator<Item = &ItemScope> {
+        self.walk().map(|nested| nested.item)
+    }
+
+    /// This item or the items nested in it that have the given itemtype.
+    pub fn items_of_type<'a>(
+        &'a self,
+        itemtype: &'a str,
+    ) -> impl Iterator<Item = &'a ItemScope> + 'a {
+        self.descendants()
+            .filter(move |item| item.has_type(itemtype))
+    }
+}
+
+fn flatten(value: &ValueType) -> Box<dyn Iterator<Item = &ValueType> + '_> {
+    match value {
+        ValueType::Array(values) => Box::new(values.iter().flat_map(flatten)),
+        value => Box::new(std::iter::once(value)),
+    }
+}
+
+fn path_values<'a, N>(
+    item: &'a ItemScope,
+    mut names: N,
+) -> Box<dyn Iterator<Item = &'a ValueType> + 'a>
+where
+    N: Iterator<Item = &'a str> + Clone + 'a,
+{
+    let Some(name) = names.next() else {
+        return Box::new(std::iter::empty());
+    };
+    if names.clone().next().is_none() {
+        return Box::new(item.values(name));
+    }
+    Box::new(
+        item.values(name)
+            .filter_map(ValueType::as_item)
+            .flat_map(move |nested| path_values(nested, names.clone())),
+    )
+}
+
+/// An item met while walking, with where it sits in the tree.
+#[derive(Debug, Clone, Copy)]
+pub struct Nested<'a> {
+    pub item: &'a ItemScope,
+    /// 0 for the item the walk started from
+    pub depth: usize,
+    /// the property the item is a value of, `None` for the starting item
+    pub name: Option<&'a Name>,
+    pub parent: Option<&'a ItemScope>,
+}
+
+pub struct Walk<'a> {
+    stack: Vec<Nested<'a>>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Nested<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.stack.pop()?;
+        let children = current
+            .item
+            .items
+            .iter()
+            .flat_map(|p| flatten(&p.value).map(move |value| (&p.name, value)))
+            .filter_map(|(name, value)| {
+                value.as_item().map(|item| Nested {
+                    item,
+                    depth: current.depth + 1,
+                    name: Some(name),
+                    parent: Some(current.item),
+                })
+            })
+            .collect::<Vec<_>>();
+        self.stack.extend(children.into_iter().rev());
+        Some(current)
+    }
+}
+
+/// The same helpers over all the items of a page.
+pub trait Query {
+    /// Items, top level or nested, that have the given itemtype.
+    fn items_of_type<'a>(&'a self, itemtype: &'a str) -> impl Iterator<Item = &'a ItemScope>;
+
+    /// Values at the end of a dotted path, starting from every top level item.
+    fn path<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a ValueType>;
+
+    /// Like `path`, with the names given one by one.
+    fn path_of<'a>(&'a self, names: &'a [&'a str]) -> impl Iterator<Item = &'a ValueType>;
+
+    /// Every item, top level ones being at depth 0.
+    fn walk(&self) -> impl Iterator<Item = Nested<'_>>;
+}
+
+impl Query for VecDeque<ItemScope> {
+    fn items_of_type<'a>(&'a self, itemtype: &'a str) -> impl Iterator<Item = &'a ItemScope> {
+        self.iter()
+            .flat_map(move |item| item.items_of_type(itemtype))
+    }
+
+    fn path<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a ValueType> {
+        self.iter().flat_map(move |item| item.path(path))
+    }
+
+    fn path_of<'a>(&'a self, names: &'a [&'a str]) -> impl Iterator<Item = &'a ValueType> {
+        self.iter().flat_map(move |item| item.path_of(names))
+    }
+
+    fn walk(&self) -> impl Iterator<Item = Nested<'_>> {
+        self.iter().flat_map(ItemScope::walk)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        domain::{Name, ValueType},
+        parse_html,
+        query::Query,
+    };
+
+    #[test]
+    fn test_query() {
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/ItemList">
+            <div itemprop="itemListElement" itemscope itemtype="https://schema.org/Product">
+                <span itemprop="name">Fridge</span>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <data itemprop="price" value="499">499</data>
+                </div>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <data itemprop="price" value="459.5">459.5</data>
+                </div>
+            </div>
+            <div itemprop="itemListElement" itemscope itemtype="https://schema.org/Product">
+                <span itemprop="name">Oven</span>
+                <meta itemprop="keywords" content="kitchen">
+                <meta itemprop="keywords" content="cooking">
+            </div>
+        </div>
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="http://schema.org/name">Toaster</span>
+        </div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        let names = items
+            .items_of_type("https://schema.org/Product")
+            .filter_map(|product| product.value("name"))
+            .filter_map(ValueType::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Fridge", "Oven", "Toaster"]);
+
+        let prices = items
+            .path("itemListElement.offers.price")
+            .filter_map(ValueType::as_f64)
+            .collect::<Vec<_>>();
+        assert_eq!(prices, [499.0, 459.5]);
+
+        let oven = items
+            .items_of_type("https://schema.org/Product")
+            .nth(1)
+            .unwrap();
+        let keywords = oven
+            .values("keywords")
+            .filter_map(ValueType::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(keywords, ["kitchen", "cooking"]);
+        assert_eq!(oven.path("offers.price").count(), 0);
+        assert_eq!(items[0].descendants().count(), 5);
+        assert_eq!(items.path("name").count(), 1);
+
+        // absolute url names hold dots
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/Product">
+            <div itemprop="https://schema.org/offers" itemscope>
+                <data itemprop="https://schema.org/price" value="499">499</data>
+            </div>
+        </div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        let prices = items
+            .path_of(&["https://schema.org/offers", "https://schema.org/price"])
+            .filter_map(ValueType::as_i64)
+            .collect::<Vec<_>>();
+        assert_eq!(prices, [499]);
+        assert_eq!(items.path_of(&["offers", "price"]).count(), 1);
+        assert_eq!(items.path("https://schema.org/offers.price").count(), 0);
+        assert_eq!(items[0].path_of(&[]).count(), 0);
+    }
+
+    #[test]
+    fn test_walk() {
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/Product">
+            <div itemprop="review" itemscope itemtype="https://schema.org/Review">
+                <div itemprop="author" itemscope itemtype="https://schema.org/Person"></div>
+            </div>
+            <div itemprop="review" itemscope itemtype="https://schema.org/Review"></div>
+        </div>
+        <div itemscope itemtype="https://schema.org/Review"></div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        let walked = items
+            .walk()
+            .map(|nested| {
+                (
+                    nested.item.itemtype[0].as_str(),
+                    nested.depth,
+                    nested.name.map(Name::as_str),
+                    nested.parent.map(|parent| parent.itemtype[0].as_str()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            walked,
+            [
+                ("https://schema.org/Product", 0, None, None),
+                (
+                    "https://schema.org/Review",
+                    1,
+                    Some("review"),
+                    Some("https://schema.org/Product")
+                ),
+                (
+                    "https://schema.org/Person",
+                    2,
+                    Some("author"),
+                    Some("https://schema.org/Review")
+                ),
+                (
+                    "https://schema.org/Review",
+                    1,
+                    Some("review"),
+                    Some("https://schema.org/Product")
+                ),
+                ("https://schema.org/Review", 0, None, None),
+            ]
+        );
+        assert_eq!(items.items_of_type("https://schema.org/Review").count(), 3);
+    }
+}
@@ -0,0 +1,398 @@
+//! Conversion of items to RDF following the W3C "Microdata to RDF" note.
+//!
+//! Items without an `itemid`, or whose `itemid` is not an absolute url (the
+//! parser resolves it only when given a base url), become blank nodes, each
+//! `itemtype` becomes an `rdf:type` triple and property names that are not
+//! absolute urls are expanded against the vocabulary of the first `itemtype`
+//! (inherited by nested untyped items).
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::{self, Write},
+    sync::Arc,
+};
+
+use log::debug;
+use url::Url;
+
+use crate::domain::{ItemScope, Name, Number, Numeric, ValueType};
+
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+pub const XSD_NS: &str = "http://www.w3.org/2001/XMLSchema#";
+/// Used to build a property iri when there is no vocabulary to expand a name against.
+pub const MD_NS: &str = "http://www.w3.org/ns/md?type=&prop=";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Term {
+    Iri(String),
+    BlankNode(String),
+    Literal {
+        value: String,
+        datatype: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Triple {
+    pub subject: Term,
+    pub predicate: Term,
+    pub object: Term,
+}
+
+pub fn to_triples(items: &VecDeque<ItemScope>) -> Vec<Triple> {
+    let mut generator = Generator::default();
+    for item in items {
+        generator.item(item, None);
+    }
+    generator.triples
+}
+
+pub fn to_ntriples(triples: &[Triple]) -> String {
+    let mut out = String::new();
+    for triple in triples {
+        let _ = writeln!(out, "{triple}");
+    }
+    out
+}
+
+pub fn to_turtle(triples: &[Triple]) -> String {
+    // group by subject, then predicate, keeping the order in which they were first seen
+    type Objects<'a> = Vec<&'a Term>;
+    type Predicates<'a> = (Vec<(&'a Term, Objects<'a>)>, HashMap<&'a Term, usize>);
+    let mut subjects: Vec<(&Term, Predicates)> = vec![];
+    let mut subject_index: HashMap<&Term, usize> = HashMap::new();
+    for Triple {
+        subject,
+        predicate,
+        object,
+    } in triples
+    {
+        let idx = *subject_index.entry(subject).or_insert_with(|| {
+            subjects.push((subject, Default::default()));
+            subjects.len() - 1
+        });
+        let (predicates, predicate_index) = &mut subjects[idx].1;
+        match predicate_index.get(predicate) {
+            Some(&idx) => predicates[idx].1.push(object),
+            None => {
+                predicate_index.insert(predicate, predicates.len());
+                predicates.push((predicate, vec![object]));
+            }
+        }
+    }
+
+    let mut out = format!("@prefix rdf: <{RDF_NS}> .\n@prefix xsd: <{XSD_NS}> .\n");
+    for (subject, (predicates, _)) in subjects {
+        let _ = write!(out, "\n{subject}");
+        for (idx, (predicate, objects)) in predicates.iter().enumerate() {
+            let separator = if idx == 0 { " " } else { " ;\n    " };
+            let predicate = match predicate {
+                Term::Iri(iri) if iri == RDF_TYPE => "a".to_string(),
+                p => p.to_string(),
+            };
+            let objects = objects
+                .iter()
+                .map(|o| turtle_term(o))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = write!(out, "{separator}{predicate} {objects}");
+        }
+        out.push_str(" .\n");
+    }
+    out
+}
+
+fn turtle_term(term: &Term) -> String {
+    match term {
+        Term::Literal {
+            value,
+            datatype: Some(datatype),
+        } if datatype.starts_with(XSD_NS) => {
+            format!(
+                "\"{}\"^^xsd:{}",
+                escape_literal(value),
+                &datatype[XSD_NS.len()..]
+            )
+        }
+        term => term.to_string(),
+    }
+}
+
+#[derive(Default)]
+struct Generator {
+    triples: Vec<Triple>,
+    blank_nodes: usize,
+    // the same nested item can be referenced more than once
+    subjects: BTreeMap<*const ItemScope, Term>,
+}
+
+impl Generator {
+    fn item(&mut self, item: &ItemScope, vocabulary: Option<&str>) -> Term {
+        let subject = match &item.itemid {
+            Some(itemid) if Url::parse(itemid).is_ok() => Term::Iri(itemid.clone()),
+            itemid => {
+                if let Some(itemid) = itemid {
+                    debug!("itemid {itemid} is not an absolute url, using a blank node");
+                }
+                self.blank_nodes += 1;
+                Term::BlankNode(format!("b{}", self.blank_nodes - 1))
+            }
+        };
+        for itemtype in &item.itemtype {
+            self.triples.push(Triple {
+                subject: subject.clone(),
+                predicate: Term::Iri(RDF_TYPE.into()),
+                object: Term::Iri(itemtype.clone()),
+            });
+        }
+        let vocabulary = item
+            .itemtype
+            .first()
+            .map(|t| vocabulary_of(t))
+            .or(vocabulary);
+        for property in &item.items {
+            let predicate = Term::Iri(match &property.name {
+                Name::Url(url) => url.clone(),
+                Name::String(name) => match vocabulary {
+                    Some(vocabulary) => format!("{vocabulary}{name}"),
+                    None => format!("{MD_NS}{name}"),
+                },
+            });
+            self.values(&subject, &predicate, &property.value, vocabulary);
+        }
+        subject
+    }
+
+    fn values(
+        &mut self,
+        subject: &Term,
+        predicate: &Term,
+        value: &ValueType,
+        vocabulary: Option<&str>,
+    ) {
+        let object = match value {
+            ValueType::Array(values) => {
+                for value in values {
+                    self.values(subject, predicate, value, vocabulary);
+                }
+                return;
+            }
+            ValueType::ScopeRef(item) => {
+                let key = Arc::as_ptr(item);
+                match self.subjects.get(&key) {
+                    Some(object) => object.clone(),
+                    None => {
+                        let object = self.item(item, vocabulary);
+                        self.subjects.insert(key, object.clone());
+                        object
+                    }
+                }
+            }
+            ValueType::Url(url) => Term::Iri(url.clone()),
+            ValueType::Empty => literal("", None),
+            ValueType::String(s) => literal(s, None),
+            ValueType::Meter(n) | ValueType::Number(n) => {
+                literal(n.lexical(), Some(numeric_datatype(n)))
+            }
+            ValueType::Time(t) => literal(t.lexical(), t.kind().xsd_datatype()),
+        };
+        self.triples.push(Triple {
+            subject: subject.clone(),
+            predicate: predicate.clone(),
+            object,
+        });
+    }
+}
+
+fn literal(value: &str, datatype: Option<&str>) -> Term {
+    Term::Literal {
+        value: value.to_string(),
+        datatype: datatype.map(|d| format!("{XSD_NS}{d}")),
+    }
+}
+
+/// The itemtype up to and including its last `#`, or else its last `/`.
+pub(crate) fn vocabulary_of(itemtype: &str) -> &str {
+    let end = itemtype
+        .rfind('#')
+        .or_else(|| itemtype.rfind('/'))
+        .map(|idx| idx + 1)
+        .unwrap_or(itemtype.len());
+    &itemtype[..end]
+}
+
+pub(crate) fn numeric_datatype(number: &Number) -> &'static str {
+    match number.value() {
+        Numeric::Integer(_) => "integer",
+        Numeric::Float(_) => "double",
+    }
+}
+
+fn escape_literal(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_iri(iri: &str) -> String {
+    let mut escaped = String::with_capacity(iri.len());
+    for c in iri.chars() {
+        match c {
+            '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' | '\u{0}'..='\u{20}' => {
+                let _ = write!(escaped, "\\u{:04X}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Iri(iri) => write!(f, "<{}>", escape_iri(iri)),
+            Term::BlankNode(id) => write!(f, "_:{id}"),
+            Term::Literal {
+                value,
+                datatype: None,
+            } => write!(f, "\"{}\"", escape_literal(value)),
+            Term::Literal {
+                value,
+                datatype: Some(datatype),
+            } => write!(
+                f,
+                "\"{}\"^^<{}>",
+                escape_literal(value),
+                escape_iri(datatype)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Triple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} .", self.subject, self.predicate, self.object)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        parse_html,
+        rdf::{self, Term, Triple},
+    };
+
+    #[test]
+    fn test_to_ntriples() {
+        let html = r#"
+            <div itemscope itemtype="http://schema.org/Product" itemid="http://bittich.be/p/1">
+                <span itemprop="name">Panasonic "White" 60L Refrigerator</span>
+                <a itemprop="url" href="http://bittich.be/p/1.html">link</a>
+                <div itemprop="aggregateRating"
+                    itemscope itemtype="http://schema.org/AggregateRating">
+                    <meter itemprop="ratingValue" min=0 value=3.5 max=5>Rated 3.5/5</meter>
+                    <meter itemprop="bestRating" min=0 value=5 max=5>5</meter>
+                </div>
+                <time itemprop="releaseDate" datetime="2009-05-10">May 10th 2009</time>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let triples = rdf::to_triples(&res);
+        assert_eq!(
+            rdf::to_ntriples(&triples),
+            r#"<http://bittich.be/p/1> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://schema.org/Product> .
+<http://bittich.be/p/1> <http://schema.org/name> "Panasonic \"White\" 60L Refrigerator" .
+<http://bittich.be/p/1> <http://schema.org/url> <http://bittich.be/p/1.html> .
+_:b0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://schema.org/AggregateRating> .
+_:b0 <http://schema.org/ratingValue> "3.5"^^<http://www.w3.org/2001/XMLSchema#double> .
+_:b0 <http://schema.org/bestRating> "5"^^<http://www.w3.org/2001/XMLSchema#integer> .
+<http://bittich.be/p/1> <http://schema.org/aggregateRating> _:b0 .
+<http://bittich.be/p/1> <http://schema.org/releaseDate> "2009-05-10"^^<http://www.w3.org/2001/XMLSchema#date> .
+"#
+        );
+
+        // itemids that are not absolute urls cannot be iris
+        let html = r#"
+            <div itemscope itemid="123"><span itemprop="a">1</span></div>
+            <div itemscope itemid="/p/1"><span itemprop="a">2</span></div>
+            <div itemscope itemid="urn:isbn:0-330-34032-8"><span itemprop="a">3</span></div>
+        "#;
+        let subjects = rdf::to_triples(&parse_html("", html).unwrap())
+            .into_iter()
+            .map(|triple| triple.subject)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            subjects,
+            [
+                Term::BlankNode("b0".into()),
+                Term::BlankNode("b1".into()),
+                Term::Iri("urn:isbn:0-330-34032-8".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vocabulary_inheritance() {
+        let html = r#"
+            <div itemscope itemtype="http://microformats.org/profile/hcard">
+                <span itemprop="n" itemscope>
+                    <span itemprop="given-name">Jack</span>
+                </span>
+            </div>
+            <div itemscope><span itemprop="flavor">Lemon sorbet</span></div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        let triples = rdf::to_triples(&res);
+        assert!(triples.contains(&Triple {
+            subject: Term::BlankNode("b1".into()),
+            predicate: Term::Iri("http://microformats.org/profile/given-name".into()),
+            object: Term::Literal {
+                value: "Jack".into(),
+                datatype: None
+            }
+        }));
+        assert!(triples.contains(&Triple {
+            subject: Term::BlankNode("b2".into()),
+            predicate: Term::Iri("http://www.w3.org/ns/md?type=&prop=flavor".into()),
+            object: Term::Literal {
+                value: "Lemon sorbet".into(),
+                datatype: None
+            }
+        }));
+    }
+
+    #[test]
+    fn test_to_turtle() {
+        let html = r#"
+            <div itemscope itemtype="http://schema.org/Person">
+                <span itemprop="name">Neil</span>
+                <span itemprop="nationality">British</span>
+                <time itemprop="birthDate" datetime="1970-01-01">1970</time>
+                <p itemprop="knows">Amanda</p>
+                <p itemprop="knows">Daniel</p>
+            </div>
+        "#;
+        let res = parse_html("", html).unwrap();
+        assert_eq!(
+            rdf::to_turtle(&rdf::to_triples(&res)),
+            r#"@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .
+@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+
+_:b0 a <http://schema.org/Person> ;
+    <http://schema.org/name> "Neil" ;
+    <http://schema.org/nationality> "British" ;
+    <http://schema.org/birthDate> "1970-01-01"^^xsd:date ;
+    <http://schema.org/knows> "Amanda", "Daniel" .
+"#
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Renders items back into microdata annotated html.
+//!
+//! Each value gets the element the parser reads it from: `<a href>` for urls,
+//! `<time datetime>`, `<meter value>`, `<data value>`, `<span>` for text and
+//! `<meta content>` for text that would not survive being read back from an
+//! element's content (empty, or with leading or trailing whitespace).
+//!
+//! Parsing the output with an empty base url gives back the same items, as long
+//! as they are what the parser produces: absolute itemtypes, and url values
+//! in the normalized form of the `url` crate.
+use std::collections::VecDeque;
+
+use crate::domain::{ItemScope, ValueType};
+
+pub fn to_html(items: &VecDeque<ItemScope>) -> String {
+    let mut html = String::new();
+    for item in items {
+        write_item(&mut html, item, None);
+    }
+    html
+}
+
+pub fn item_to_html(item: &ItemScope) -> String {
+    let mut html = String::new();
+    write_item(&mut html, item, None);
+    html
+}
+
+fn write_item(html: &mut String, item: &ItemScope, itemprop: Option<&str>) {
+    html.push_str("<div");
+    if let Some(itemprop) = itemprop {
+        write_attr(html, "itemprop", itemprop);
+    }
+    html.push_str(" itemscope");
+    if !item.itemtype.is_empty() {
+        write_attr(html, "itemtype", &item.itemtype.join(" "));
+    }
+    if let Some(itemid) = &item.itemid {
+        write_attr(html, "itemid", itemid);
+    }
+    html.push('>');
+    for property in &item.items {
+        write_value(html, property.name.as_str(), &property.value);
+    }
+    html.push_str("</div>");
+}
+
+fn write_value(html: &mut String, itemprop: &str, value: &ValueType) {
+    let (tag, attr, content) = match value {
+        ValueType::ScopeRef(item) => return write_item(html, item, Some(itemprop)),
+        ValueType::Array(values) => {
+            for value in values {
+                write_value(html, itemprop, value);
+            }
+            return;
+        }
+        ValueType::Empty => ("meta", None, None),
+        ValueType::Url(url) => ("a", Some(("href", url.as_str())), None),
+        ValueType::Meter(n) => ("meter", Some(("value", n.lexical())), None),
+        ValueType::Number(n) => ("data", Some(("value", n.lexical())), None),
+        ValueType::Time(t) => ("time", Some(("datetime", t.lexical())), None),
+        ValueType::String(s) if s.is_empty() || s.trim() != s => {
+            ("meta", Some(("content", s.as_str())), None)
+        }
+        ValueType::String(s) => ("span", None, Some(s.as_str())),
+    };
+    html.push('<');
+    html.push_str(tag);
+    write_attr(html, "itemprop", itemprop);
+    if let Some((name, value)) = attr {
+        write_attr(html, name, value);
+    }
+    html.push('>');
+    if tag != "meta" {
+        if let Some(content) = content {
+            escape(html, content, false);
+        }
+        html.push_str("</");
+        html.push_str(tag);
+        html.push('>');
+    }
+}
+
+fn write_attr(html: &mut String, name: &str, value: &str) {
+    html.push(' ');
+    html.push_str(name);
+    html.push_str("=\"");
+    escape(html, value, true);
+    html.push('"');
+}
+
+fn escape(html: &mut String, text: &str, in_attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' if in_attribute => html.push_str("&quot;"),
+            // the parser turns a raw carriage return into a line feed
+            '\r' => html.push_str("&#13;"),
+            c => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::VecDeque, sync::Arc};
+
+    use crate::{
+        datetime::Time,
+        domain::{ItemScope, Name, Number, Property, ValueType},
+        parse_html, render,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let items = VecDeque::from([ItemScope {
+            itemid: Some("urn:isbn:0-330-34032-8".into()),
+            itemtype: vec!["https://schema.org/Book".into()],
+            items: VecDeque::from([
+                Property::new(
+                    Name::String("name".into()),
+                    ValueType::String("Tom & \"Jerry\" <3".into()),
+                ),
+                Property::new(
+                    Name::Url("https://schema.org/url".into()),
+                    ValueType::Url("https://bittich.be/book?id=1&lang=en".into()),
+                ),
+                Property::new(
+                    Name::String("note".into()),
+                    ValueType::String("  padded\r".into()),
+                ),
+                Property::new(
+                    Name::String("lines".into()),
+                    ValueType::String("a\rb".into()),
+                ),
+                Property::new(Name::String("empty".into()), ValueType::Empty),
+                Property::new(
+                    Name::String("keywords".into()),
+                    ValueType::Array(VecDeque::from([
+                        ValueType::String("a".into()),
+                        ValueType::String("b".into()),
+                    ])),
+                ),
+                Property::new(
+                    Name::String("rating".into()),
+                    ValueType::Meter(Number::parse("4.5").unwrap()),
+                ),
+                Property::new(
+                    Name::String("pages".into()),
+                    ValueType::Number(Number::parse("320").unwrap()),
+                ),
+                Property::new(
+                    Name::String("published".into()),
+                    ValueType::Time(Time::parse("1996-01-26").unwrap()),
+                ),
+                Property::new(
+                    Name::String("author".into()),
+                    ValueType::ScopeRef(Arc::new(ItemScope {
+                        itemtype: vec!["https://schema.org/Person".into()],
+                        items: VecDeque::from([Property::new(
+                            Name::String("name".into()),
+                            ValueType::String("Peter F. Hamilton".into()),
+                        )]),
+                        ..Default::default()
+                    })),
+                ),
+            ]),
+            ..Default::default()
+        }]);
+        let html = render::to_html(&items);
+        assert!(!html.contains('\r'));
+        assert_eq!(parse_html("", &html).unwrap(), items);
+    }
+
+    #[test]
+    fn test_parsed_round_trip() {
+        let html = r#"
+        <div itemscope itemtype="http://schema.org/Product">
+            <span itemprop="name">Panasonic White 60L Refrigerator</span>
+            <img itemprop="image" src="http://bittich.be/fridge.jpg" alt="">
+            <div itemprop="aggregateRating" itemscope itemtype="http://schema.org/AggregateRating">
+                <meter itemprop="ratingValue" min=0 value=3.5 max=5>Rated 3.5/5</meter>
+                (based on <span itemprop="reviewCount">11</span> customer reviews)
+            </div>
+        </div>
+        <div itemscope><time itemprop="date" datetime="2011-11-18">Nov 18</time></div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        assert_eq!(parse_html("", &render::to_html(&items)).unwrap(), items);
+    }
+}
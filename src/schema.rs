@@ -0,0 +1,493 @@
+//! Checks items against the schema.org vocabulary.
+//!
+//! ```
+//! use microdata::schema::{Finding, Vocabulary};
+//!
+//! let html = r#"
+//!     <div itemscope itemtype="https://schema.org/Product">
+//!         <span itemprop="name">Fridge</span>
+//!         <span itemprop="startDate">2024-01-01</span>
+//!     </div>
+//! "#;
+//! let items = microdata::parse_html("", html).unwrap();
+//! let findings = Vocabulary::schema_org().validate(&items);
+//! assert!(matches!(&findings[..], [Finding::UnknownProperty { path, .. }] if path == "[0].startDate"));
+//! ```
+//!
+//! The bundled vocabulary (`src/schema/schemaorg.json`) has every schema.org
+//! class with its supertypes and every property with its domain and range. It
+//! reports unknown types and properties, properties used on a type they are not
+//! defined for, and nested items of a type outside the range of their property.
+//! `examples/schema_org.rs` regenerates it from a schema.org release.
+//!
+//! `Vocabulary::from_schema_org_jsonld` loads a release
+//! (`schemaorg-current-https.jsonld`) directly. `Vocabulary::from_json` loads
+//! one in the format of the bundled file; without `"complete": true`, terms
+//! it does not know are left alone, and so are the items that have such a type.
+//!
+//! Only itemtypes and property names of schema.org (`http` or `https`) are
+//! checked. Paths start with the index of the top level item (`[0]`), followed
+//! by the property names, multi-valued properties giving the index of the value
+//! (`[0].review[1].author`).
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::LazyLock,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    Error,
+    domain::{ItemScope, Name, ValueType},
+};
+
+const SCHEMA_ORG: &str = include_str!("schema/schemaorg.json");
+
+static SCHEMA_ORG_VOCABULARY: LazyLock<Vocabulary> = LazyLock::new(|| {
+    Vocabulary::from_json(SCHEMA_ORG).expect("the bundled schema.org vocabulary is valid")
+});
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Vocabulary {
+    /// type name to its direct supertypes
+    types: HashMap<String, Vec<String>>,
+    properties: HashMap<String, PropertyDef>,
+    /// whether terms missing from it are unknown to schema.org
+    #[serde(default)]
+    complete: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PropertyDef {
+    /// types the property can be used on
+    pub domain: Vec<String>,
+    /// types, data types included, its values are expected to have
+    pub range: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "finding")]
+pub enum Finding {
+    UnknownType {
+        path: String,
+        itemtype: String,
+    },
+    /// not a schema.org property, or not one of the types of the item
+    UnknownProperty {
+        path: String,
+        name: String,
+        itemtype: Vec<String>,
+    },
+    /// a nested item whose types are not in the range of the property
+    UnexpectedType {
+        path: String,
+        name: String,
+        itemtype: Vec<String>,
+        expected: Vec<String>,
+    },
+}
+
+impl Finding {
+    pub fn path(&self) -> &str {
+        match self {
+            Finding::UnknownType { path, .. }
+            | Finding::UnknownProperty { path, .. }
+            | Finding::UnexpectedType { path, .. } => path,
+        }
+    }
+}
+
+impl Vocabulary {
+    /// The bundled schema.org snapshot.
+    pub fn schema_org() -> &'static Vocabulary {
+        &SCHEMA_ORG_VOCABULARY
+    }
+
+    pub fn from_json(json: &str) -> Result<Vocabulary, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Reads the JSON-LD release of schema.org, e.g.
+    /// `https://schema.org/version/latest/schemaorg-current-https.jsonld`.
+    pub fn from_schema_org_jsonld(jsonld: &str) -> Result<Vocabulary, Error> {
+        let document: Value = serde_json::from_str(jsonld)?;
+        let graph = document["@graph"].as_array().ok_or_else(|| {
+            <Error as serde::de::Error>::custom("schema.org release without @graph")
+        })?;
+        let mut vocabulary = Vocabulary {
+            types: HashMap::new(),
+            properties: HashMap::new(),
+            complete: true,
+        };
+        for node in graph {
+            let Some(name) = node["@id"].as_str().and_then(term_name) else {
+                continue;
+            };
+            let node_types = ids(&node["@type"]);
+            if node_types.contains(&"rdfs:Class") {
+                let parents = ids(&node["rdfs:subClassOf"])
+                    .into_iter()
+                    .filter_map(term_name)
+                    .map(String::from)
+                    .collect();
+                vocabulary.types.insert(name.to_string(), parents);
+            } else if node_types.contains(&"rdf:Property") {
+                let terms = |key: &str| {
+                    ids(&node[key])
+                        .into_iter()
+                        .filter_map(term_name)
+                        .map(String::from)
+                        .collect()
+                };
+                vocabulary.properties.insert(
+                    name.to_string(),
+                    PropertyDef {
+                        domain: terms("schema:domainIncludes"),
+                        range: terms("schema:rangeIncludes"),
+                    },
+                );
+            }
+        }
+        Ok(vocabulary)
+    }
+
+    /// Whether it has every schema.org term, unknown ones being reported.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn has_type(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+
+    pub fn property(&self, name: &str) -> Option<&PropertyDef> {
+        self.properties.get(name)
+    }
+
+    /// The type and all its supertypes.
+    pub fn ancestors<'a>(&'a self, name: &'a str) -> HashSet<&'a str> {
+        let mut ancestors = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            if ancestors.insert(name) {
+                pending.extend(
+                    self.types
+                        .get(name)
+                        .into_iter()
+                        .flatten()
+                        .map(String::as_str),
+                );
+            }
+        }
+        ancestors
+    }
+
+    pub fn is_subtype(&self, name: &str, supertype: &str) -> bool {
+        self.ancestors(name).contains(supertype)
+    }
+
+    pub fn validate(&self, items: &VecDeque<ItemScope>) -> Vec<Finding> {
+        let mut findings = vec![];
+        for (i, item) in items.iter().enumerate() {
+            self.validate_item(&format!("[{i}]"), item, &mut findings);
+        }
+        findings
+    }
+
+    fn validate_item(&self, path: &str, item: &ItemScope, findings: &mut Vec<Finding>) {
+        let mut types = vec![];
+        let mut uncovered = false;
+        for itemtype in &item.itemtype {
+            let Some(name) = schema_org_name(itemtype) else {
+                continue;
+            };
+            if self.has_type(name) {
+                types.push(name);
+            } else if self.complete {
+                findings.push(Finding::UnknownType {
+                    path: path.to_string(),
+                    itemtype: itemtype.clone(),
+                });
+            } else {
+                uncovered = true;
+            }
+        }
+        // an incomplete vocabulary cannot tell which properties a type it lacks has
+        let check_properties = !types.is_empty() && !uncovered;
+        let ancestors = types
+            .iter()
+            .flat_map(|name| self.ancestors(name))
+            .collect::<HashSet<_>>();
+
+        for property in &item.items {
+            let path = format!("{path}.{}", property.name.as_str());
+            let values = match &property.value {
+                ValueType::Array(values) => values.iter().collect::<Vec<_>>(),
+                value => vec![value],
+            };
+            let definition = match property_name(&property.name) {
+                Some(name) if check_properties => {
+                    let definition = self.property(name);
+                    let in_domain = definition
+                        .filter(|def| def.domain.iter().any(|d| ancestors.contains(d.as_str())));
+                    // a name missing from an incomplete vocabulary may be a schema.org property
+                    if in_domain.is_none() && (definition.is_some() || self.complete) {
+                        findings.push(Finding::UnknownProperty {
+                            path: path.clone(),
+                            name: name.to_string(),
+                            itemtype: item.itemtype.clone(),
+                        });
+                    }
+                    in_domain.map(|def| (name, def))
+                }
+                _ => None,
+            };
+            for (i, value) in values.iter().enumerate() {
+                let ValueType::ScopeRef(nested) = value else {
+                    continue;
+                };
+                let path = if values.len() > 1 {
+                    format!("{path}[{i}]")
+                } else {
+                    path.clone()
+                };
+                if let Some((name, definition)) = definition {
+                    self.check_range(&path, name, definition, nested, findings);
+                }
+                self.validate_item(&path, nested, findings);
+            }
+        }
+    }
+
+    fn check_range(
+        &self,
+        path: &str,
+        name: &str,
+        definition: &PropertyDef,
+        nested: &ItemScope,
+        findings: &mut Vec<Finding>,
+    ) {
+        let mut types = vec![];
+        for name in nested.itemtype.iter().filter_map(|t| schema_org_name(t)) {
+            if self.has_type(name) {
+                types.push(name);
+            } else if !self.complete {
+                // it may be a subtype of the range the vocabulary does not know of
+                return;
+            }
+        }
+        if types.is_empty() {
+            return;
+        }
+        let in_range = types.into_iter().any(|nested_type| {
+            definition
+                .range
+                .iter()
+                .any(|range| self.is_subtype(nested_type, range))
+        });
+        if !in_range {
+            findings.push(Finding::UnexpectedType {
+                path: path.to_string(),
+                name: name.to_string(),
+                itemtype: nested.itemtype.clone(),
+                expected: definition.range.clone(),
+            });
+        }
+    }
+}
+
+/// `Product` for `https://schema.org/Product` or `http://schema.org/Product`.
+pub fn schema_org_name(iri: &str) -> Option<&str> {
+    iri.strip_prefix("https://schema.org/")
+        .or_else(|| iri.strip_prefix("http://schema.org/"))
+}
+
+fn property_name(name: &Name) -> Option<&str> {
+    match name {
+        Name::String(name) => Some(name),
+        Name::Url(url) => schema_org_name(url),
+    }
+}
+
+fn term_name(id: &str) -> Option<&str> {
+    id.strip_prefix("schema:").or_else(|| schema_org_name(id))
+}
+
+// the ids of a JSON-LD value that is a string, a node reference or an array of them
+fn ids(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(id) => vec![id.as_str()],
+        Value::Object(node) => node
+            .get("@id")
+            .and_then(Value::as_str)
+            .into_iter()
+            .collect(),
+        Value::Array(values) => values.iter().flat_map(ids).collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{
+        parse_html,
+        schema::{Finding, Vocabulary},
+    };
+
+    #[test]
+    fn test_validate() {
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Fridge</span>
+            <span itemprop="https://schema.org/sku">F-60</span>
+            <span itemprop="http://bittich.be/internal">42</span>
+            <div itemprop="offers" itemscope itemtype="https://schema.org/AggregateOffer">
+                <data itemprop="lowPrice" value="459">459</data>
+                <span itemprop="startDate">today</span>
+            </div>
+            <div itemprop="review" itemscope itemtype="https://schema.org/Review">
+                <div itemprop="author" itemscope itemtype="https://schema.org/Person"></div>
+            </div>
+            <div itemprop="review" itemscope itemtype="https://schema.org/Review">
+                <div itemprop="author" itemscope itemtype="https://schema.org/Offer"></div>
+            </div>
+            <div itemprop="brand" itemscope itemtype="https://schema.org/Brnd"></div>
+            <span itemprop="isbn">not for products</span>
+        </div>
+        <div itemscope itemtype="https://schema.org/LocalBusiness http://bittich.be/Shop">
+            <span itemprop="telephone">555-0100</span>
+            <div itemprop="geo" itemscope itemtype="http://schema.org/GeoCoordinates">
+                <meta itemprop="latitude" content="50.8">
+            </div>
+        </div>
+        <div itemscope itemtype="http://bittich.be/Other"><span itemprop="anything">x</span></div>
+        <div itemscope itemtype="https://schema.org/MusicGroup">
+            <span itemprop="name">Queen</span>
+            <div itemprop="event" itemscope itemtype="https://schema.org/MusicEvent">
+                <div itemprop="location" itemscope itemtype="https://schema.org/EventVenue"></div>
+            </div>
+        </div>
+        <div itemscope itemtype="https://schema.org/Service">
+            <div itemprop="provider" itemscope itemtype="https://schema.org/Organization"></div>
+            <div itemprop="offers" itemscope itemtype="https://schema.org/Offer"></div>
+        </div>
+        <div itemscope itemtype="https://schema.org/Movie">
+            <meta itemprop="duration" content="PT2H">
+            <div itemprop="author" itemscope itemtype="https://schema.org/PerformingArtsTheater"></div>
+        </div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        let findings = Vocabulary::schema_org().validate(&items);
+        assert_eq!(
+            findings,
+            [
+                Finding::UnknownProperty {
+                    path: "[0].offers.startDate".into(),
+                    name: "startDate".into(),
+                    itemtype: vec!["https://schema.org/AggregateOffer".into()],
+                },
+                Finding::UnexpectedType {
+                    path: "[0].review[1].author".into(),
+                    name: "author".into(),
+                    itemtype: vec!["https://schema.org/Offer".into()],
+                    expected: vec!["Organization".into(), "Person".into()],
+                },
+                Finding::UnknownType {
+                    path: "[0].brand".into(),
+                    itemtype: "https://schema.org/Brnd".into(),
+                },
+                Finding::UnknownProperty {
+                    path: "[0].isbn".into(),
+                    name: "isbn".into(),
+                    itemtype: vec!["https://schema.org/Product".into()],
+                },
+                Finding::UnexpectedType {
+                    path: "[5].author".into(),
+                    name: "author".into(),
+                    itemtype: vec!["https://schema.org/PerformingArtsTheater".into()],
+                    expected: vec!["Organization".into(), "Person".into()],
+                },
+            ]
+        );
+        let json = serde_json::to_value(&findings[1]).unwrap();
+        assert_eq!(json["finding"], json!("unexpectedType"));
+        assert_eq!(findings[1].path(), "[0].review[1].author");
+    }
+
+    // the shape of schemaorg-current-https.jsonld
+    const RELEASE: &str = r#"{
+        "@context": {"schema": "https://schema.org/", "rdfs": "http://www.w3.org/2000/01/rdf-schema#"},
+        "@graph": [
+            {"@id": "schema:Thing", "@type": "rdfs:Class"},
+            {"@id": "schema:Text", "@type": ["schema:DataType", "rdfs:Class"]},
+            {"@id": "schema:Product", "@type": "rdfs:Class", "rdfs:subClassOf": {"@id": "schema:Thing"}},
+            {"@id": "schema:Brand", "@type": "rdfs:Class", "rdfs:subClassOf": [{"@id": "schema:Thing"}]},
+            {"@id": "schema:InStock", "@type": "schema:ItemAvailability"},
+            {"@id": "schema:name", "@type": "rdf:Property",
+             "schema:domainIncludes": {"@id": "schema:Thing"}, "schema:rangeIncludes": {"@id": "schema:Text"}},
+            {"@id": "schema:brand", "@type": "rdf:Property",
+             "schema:domainIncludes": [{"@id": "schema:Product"}], "schema:rangeIncludes": [{"@id": "schema:Brand"}]}
+        ]
+    }"#;
+
+    #[test]
+    fn test_schema_org_release() {
+        let vocabulary = Vocabulary::from_schema_org_jsonld(RELEASE).unwrap();
+        assert!(vocabulary.is_complete());
+        assert!(Vocabulary::schema_org().is_complete());
+        assert!(vocabulary.is_subtype("Brand", "Thing"));
+        assert!(!vocabulary.has_type("InStock"));
+        assert_eq!(vocabulary.property("brand").unwrap().range, ["Brand"]);
+
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Fridge</span>
+            <span itemprop="colour">white</span>
+            <div itemprop="brand" itemscope itemtype="https://schema.org/Brnd"></div>
+        </div>
+        "#;
+        let items = parse_html("", html).unwrap();
+        assert_eq!(
+            vocabulary.validate(&items),
+            [
+                Finding::UnknownProperty {
+                    path: "[0].colour".into(),
+                    name: "colour".into(),
+                    itemtype: vec!["https://schema.org/Product".into()],
+                },
+                Finding::UnknownType {
+                    path: "[0].brand".into(),
+                    itemtype: "https://schema.org/Brnd".into(),
+                },
+            ]
+        );
+        assert!(Vocabulary::from_schema_org_jsonld("{}").is_err());
+    }
+
+    #[test]
+    fn test_vocabulary() {
+        let schema_org = Vocabulary::schema_org();
+        assert!(schema_org.is_subtype("Restaurant", "Place"));
+        assert!(schema_org.is_subtype("BlogPosting", "CreativeWork"));
+        assert!(!schema_org.is_subtype("Person", "Organization"));
+        assert!(schema_org.is_subtype("HowToStep", "ItemList"));
+        assert!(schema_org.has_type("URL") && !schema_org.has_type("InStock"));
+        assert!(schema_org.property("isbn").is_some());
+
+        let vocabulary = Vocabulary::from_json(
+            r#"{"types": {"Thing": [], "Dog": ["Thing"]},
+                "properties": {"name": {"domain": ["Thing"], "range": ["Text"]}}}"#,
+        )
+        .unwrap();
+        let items = parse_html(
+            "",
+            r#"<div itemscope itemtype="https://schema.org/Dog"><span itemprop="name">Rex</span></div>"#,
+        )
+        .unwrap();
+        assert!(vocabulary.validate(&items).is_empty());
+        assert!(Vocabulary::from_json("{}").is_err());
+    }
+}
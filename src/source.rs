@@ -0,0 +1,167 @@
+//! Keeps track of the line each element starts on. scraper's own sink drops
+//! the line numbers html5ever reports, so parsing goes through this wrapper.
+//!
+//! html5ever reports the line a token ends on, which for a start tag spread
+//! over several lines is the line of its `>`. A start tag begins right after
+//! the previous token, so its line is the one that token ended on.
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use ego_tree::NodeId;
+use html5ever::{
+    Attribute, QualName,
+    buffer_queue::BufferQueue,
+    tendril::StrTendril,
+    tokenizer::{
+        StartTag, TagToken, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerResult,
+    },
+    tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeBuilder, TreeSink},
+};
+use scraper::{Html, HtmlTreeSink};
+
+pub(crate) type Lines = HashMap<NodeId, usize>;
+
+pub(crate) fn parse_document(html: &str) -> (Html, Lines) {
+    let sink = LineSink {
+        sink: HtmlTreeSink::new(Html::new_document()),
+        line: Cell::new(1),
+        lines: RefCell::default(),
+    };
+    let tokens = LineTokens {
+        tree_builder: TreeBuilder::new(sink, Default::default()),
+        previous_line: Cell::new(1),
+    };
+    let tokenizer = Tokenizer::new(tokens, Default::default());
+    let input = BufferQueue::default();
+    input.push_back(StrTendril::from_slice(html));
+    while let TokenizerResult::Script(_) = tokenizer.feed(&input) {}
+    tokenizer.end();
+    tokenizer.sink.tree_builder.sink.finish()
+}
+
+struct LineTokens {
+    tree_builder: TreeBuilder<NodeId, LineSink>,
+    // the line the last token ended on
+    previous_line: Cell<u64>,
+}
+
+impl TokenSink for LineTokens {
+    type Handle = NodeId;
+
+    fn process_token(&self, token: Token, line_number: u64) -> TokenSinkResult<NodeId> {
+        let line = match &token {
+            TagToken(tag) if tag.kind == StartTag => self.previous_line.get(),
+            _ => line_number,
+        };
+        self.tree_builder.sink.line.set(line);
+        self.previous_line.set(line_number);
+        self.tree_builder.process_token(token, line_number)
+    }
+
+    fn end(&self) {
+        self.tree_builder.end()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&self) -> bool {
+        self.tree_builder
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
+
+struct LineSink {
+    sink: HtmlTreeSink,
+    // the line elements created now start on
+    line: Cell<u64>,
+    lines: RefCell<Lines>,
+}
+
+impl TreeSink for LineSink {
+    type Output = (Html, Lines);
+    type Handle = NodeId;
+    type ElemName<'a> = <HtmlTreeSink as TreeSink>::ElemName<'a>;
+
+    fn finish(self) -> Self::Output {
+        (self.sink.finish(), self.lines.into_inner())
+    }
+
+    fn create_element(&self, name: QualName, attrs: Vec<Attribute>, flags: ElementFlags) -> NodeId {
+        let id = self.sink.create_element(name, attrs, flags);
+        self.lines.borrow_mut().insert(id, self.line.get() as usize);
+        id
+    }
+
+    fn parse_error(&self, msg: Cow<'static, str>) {
+        self.sink.parse_error(msg)
+    }
+
+    fn get_document(&self) -> NodeId {
+        self.sink.get_document()
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a NodeId) -> Self::ElemName<'a> {
+        self.sink.elem_name(target)
+    }
+
+    fn create_comment(&self, text: StrTendril) -> NodeId {
+        self.sink.create_comment(text)
+    }
+
+    fn create_pi(&self, target: StrTendril, data: StrTendril) -> NodeId {
+        self.sink.create_pi(target, data)
+    }
+
+    fn append(&self, parent: &NodeId, child: NodeOrText<NodeId>) {
+        self.sink.append(parent, child)
+    }
+
+    fn append_based_on_parent_node(
+        &self,
+        element: &NodeId,
+        prev_element: &NodeId,
+        child: NodeOrText<NodeId>,
+    ) {
+        self.sink
+            .append_based_on_parent_node(element, prev_element, child)
+    }
+
+    fn append_doctype_to_document(
+        &self,
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril,
+    ) {
+        self.sink
+            .append_doctype_to_document(name, public_id, system_id)
+    }
+
+    fn get_template_contents(&self, target: &NodeId) -> NodeId {
+        self.sink.get_template_contents(target)
+    }
+
+    fn same_node(&self, x: &NodeId, y: &NodeId) -> bool {
+        self.sink.same_node(x, y)
+    }
+
+    fn set_quirks_mode(&self, mode: QuirksMode) {
+        self.sink.set_quirks_mode(mode)
+    }
+
+    fn append_before_sibling(&self, sibling: &NodeId, new_node: NodeOrText<NodeId>) {
+        self.sink.append_before_sibling(sibling, new_node)
+    }
+
+    fn add_attrs_if_missing(&self, target: &NodeId, attrs: Vec<Attribute>) {
+        self.sink.add_attrs_if_missing(target, attrs)
+    }
+
+    fn remove_from_parent(&self, target: &NodeId) {
+        self.sink.remove_from_parent(target)
+    }
+
+    fn reparent_children(&self, node: &NodeId, new_parent: &NodeId) {
+        self.sink.reparent_children(node, new_parent)
+    }
+}
@@ -0,0 +1,258 @@
+//! Depth first traversal of items, properties and values.
+//!
+//! ```
+//! use microdata::{domain::ItemScope, visit::{self, Visitor}};
+//!
+//! #[derive(Default)]
+//! struct Depth {
+//!     current: usize,
+//!     max: usize,
+//! }
+//!
+//! impl Visitor for Depth {
+//!     fn enter_item(&mut self, _: &ItemScope) {
+//!         self.current += 1;
+//!         self.max = self.max.max(self.current);
+//!     }
+//!
+//!     fn leave_item(&mut self, _: &ItemScope) {
+//!         self.current -= 1;
+//!     }
+//! }
+//!
+//! let html = r#"<div itemscope><div itemprop="a" itemscope></div></div>"#;
+//! let items = microdata::parse_html("", html).unwrap();
+//! let mut depth = Depth::default();
+//! visit::walk(&mut depth, &items);
+//! assert_eq!(depth.max, 2);
+//! ```
+//!
+//! Nested items are entered like top level ones, and the values of a
+//! multi-valued property are visited one after the other. `visit_value` is
+//! called for every value before the hook of its variant.
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::{
+    datetime::Time,
+    domain::{ItemScope, Number, Property, ValueType},
+};
+
+#[allow(unused_variables)]
+pub trait Visitor {
+    fn enter_item(&mut self, item: &ItemScope) {}
+    fn leave_item(&mut self, item: &ItemScope) {}
+    fn enter_property(&mut self, property: &Property) {}
+    fn leave_property(&mut self, property: &Property) {}
+    fn visit_value(&mut self, value: &ValueType) {}
+    fn visit_empty(&mut self) {}
+    fn visit_url(&mut self, url: &str) {}
+    fn visit_string(&mut self, value: &str) {}
+    fn visit_meter(&mut self, value: &Number) {}
+    fn visit_number(&mut self, value: &Number) {}
+    fn visit_time(&mut self, value: &Time) {}
+}
+
+/// Like `Visitor`, with mutable access. Nested items shared with other
+/// values are cloned before being changed.
+#[allow(unused_variables)]
+pub trait VisitorMut {
+    fn enter_item(&mut self, item: &mut ItemScope) {}
+    fn leave_item(&mut self, item: &mut ItemScope) {}
+    fn enter_property(&mut self, property: &mut Property) {}
+    fn leave_property(&mut self, property: &mut Property) {}
+    /// the value can be replaced, the hook of its new variant is called next
+    fn visit_value(&mut self, value: &mut ValueType) {}
+    fn visit_empty(&mut self) {}
+    fn visit_url(&mut self, url: &mut String) {}
+    fn visit_string(&mut self, value: &mut String) {}
+    fn visit_meter(&mut self, value: &mut Number) {}
+    fn visit_number(&mut self, value: &mut Number) {}
+    fn visit_time(&mut self, value: &mut Time) {}
+}
+
+pub fn walk<V: Visitor + ?Sized>(visitor: &mut V, items: &VecDeque<ItemScope>) {
+    for item in items {
+        walk_item(visitor, item);
+    }
+}
+
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, item: &ItemScope) {
+    visitor.enter_item(item);
+    for property in &item.items {
+        visitor.enter_property(property);
+        walk_value(visitor, &property.value);
+        visitor.leave_property(property);
+    }
+    visitor.leave_item(item);
+}
+
+pub fn walk_value<V: Visitor + ?Sized>(visitor: &mut V, value: &ValueType) {
+    if let ValueType::Array(values) = value {
+        for value in values {
+            walk_value(visitor, value);
+        }
+        return;
+    }
+    visitor.visit_value(value);
+    match value {
+        ValueType::Empty => visitor.visit_empty(),
+        ValueType::Url(url) => visitor.visit_url(url),
+        ValueType::String(s) => visitor.visit_string(s),
+        ValueType::Meter(n) => visitor.visit_meter(n),
+        ValueType::Number(n) => visitor.visit_number(n),
+        ValueType::Time(t) => visitor.visit_time(t),
+        ValueType::ScopeRef(item) => walk_item(visitor, item),
+        ValueType::Array(_) => unreachable!("arrays are walked above"),
+    }
+}
+
+pub fn walk_mut<V: VisitorMut + ?Sized>(visitor: &mut V, items: &mut VecDeque<ItemScope>) {
+    for item in items {
+        walk_item_mut(visitor, item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut ItemScope) {
+    visitor.enter_item(item);
+    for property in &mut item.items {
+        visitor.enter_property(property);
+        walk_value_mut(visitor, &mut property.value);
+        visitor.leave_property(property);
+    }
+    visitor.leave_item(item);
+}
+
+pub fn walk_value_mut<V: VisitorMut + ?Sized>(visitor: &mut V, value: &mut ValueType) {
+    if let ValueType::Array(values) = value {
+        for value in values {
+            walk_value_mut(visitor, value);
+        }
+        return;
+    }
+    visitor.visit_value(value);
+    match value {
+        ValueType::Empty => visitor.visit_empty(),
+        ValueType::Url(url) => visitor.visit_url(url),
+        ValueType::String(s) => visitor.visit_string(s),
+        ValueType::Meter(n) => visitor.visit_meter(n),
+        ValueType::Number(n) => visitor.visit_number(n),
+        ValueType::Time(t) => visitor.visit_time(t),
+        ValueType::ScopeRef(item) => walk_item_mut(visitor, Arc::make_mut(item)),
+        ValueType::Array(values) => {
+            // visit_value turned the value into an array
+            for value in values {
+                walk_value_mut(visitor, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        domain::{ItemScope, Number, Property, ValueType},
+        parse_html,
+        visit::{self, Visitor, VisitorMut},
+    };
+
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn enter_item(&mut self, item: &ItemScope) {
+            self.0.push(format!("item {:?}", item.itemtype));
+        }
+
+        fn leave_item(&mut self, _: &ItemScope) {
+            self.0.push("/item".into());
+        }
+
+        fn enter_property(&mut self, property: &Property) {
+            self.0.push(format!("property {}", property.name.as_str()));
+        }
+
+        fn visit_url(&mut self, url: &str) {
+            self.0.push(format!("url {url}"));
+        }
+
+        fn visit_string(&mut self, value: &str) {
+            self.0.push(format!("string {value}"));
+        }
+
+        fn visit_meter(&mut self, value: &Number) {
+            self.0.push(format!("meter {value}"));
+        }
+    }
+
+    const HTML: &str = r#"
+    <div itemscope itemtype="https://schema.org/Product">
+        <a itemprop="url" href="https://bittich.be/fridge">fridge</a>
+        <span itemprop="color">white</span>
+        <span itemprop="color">grey</span>
+        <div itemprop="aggregateRating" itemscope itemtype="https://schema.org/AggregateRating">
+            <meter itemprop="ratingValue" min=0 value=3.5 max=5>3.5/5</meter>
+        </div>
+    </div>
+    "#;
+
+    #[test]
+    fn test_visitor() {
+        let items = parse_html("", HTML).unwrap();
+        let mut trace = Trace::default();
+        visit::walk(&mut trace, &items);
+        assert_eq!(
+            trace.0,
+            [
+                r#"item ["https://schema.org/Product"]"#,
+                "property url",
+                "url https://bittich.be/fridge",
+                "property color",
+                "string white",
+                "string grey",
+                "property aggregateRating",
+                r#"item ["https://schema.org/AggregateRating"]"#,
+                "property ratingValue",
+                "meter 3.5",
+                "/item",
+                "/item",
+            ]
+        );
+    }
+
+    struct Uppercase;
+
+    impl VisitorMut for Uppercase {
+        fn visit_value(&mut self, value: &mut ValueType) {
+            if let ValueType::Meter(n) = value {
+                *value = ValueType::String(n.to_string());
+            }
+        }
+
+        fn visit_string(&mut self, value: &mut String) {
+            *value = value.to_uppercase();
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut items = parse_html("", HTML).unwrap();
+        let shared = items[0].items[2].value.clone();
+        visit::walk_mut(&mut Uppercase, &mut items);
+        let colors = items[0]
+            .values("color")
+            .filter_map(ValueType::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(colors, ["WHITE", "GREY"]);
+        assert_eq!(
+            items[0].path("aggregateRating.ratingValue").next(),
+            Some(&ValueType::String("3.5".into()))
+        );
+        // the clone taken before still points to the untouched item
+        assert_eq!(
+            shared
+                .as_item()
+                .and_then(|rating| rating.value("ratingValue")),
+            Some(&ValueType::Meter(Number::parse("3.5").unwrap()))
+        );
+    }
+}